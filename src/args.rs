@@ -1,11 +1,15 @@
 use {
     std::{
         collections::{
+            BTreeMap,
             BTreeSet,
             HashSet
         },
         env,
-        fs::File,
+        fs::{
+            self,
+            File
+        },
         io::{
             self,
             BufReader,
@@ -13,13 +17,31 @@ use {
             prelude::*,
             stdout
         },
-        path::PathBuf,
-        str::FromStr
+        ops::RangeInclusive,
+        path::{
+            Path,
+            PathBuf
+        },
+        str::FromStr,
+        sync::Arc,
+        time::Duration
     },
     css_color_parser::Color,
+    itertools::Itertools as _,
     smart_default::SmartDefault,
+    url::Url,
     crate::{
-        art::ArtHandler,
+        art::{
+            ArtHandler,
+            ImageProvider
+        },
+        booster,
+        db::DbSource,
+        decklist::{
+            self,
+            InputFormat,
+            RemoteDecklist
+        },
         mse::DataFile,
         util::{
             Error,
@@ -32,14 +54,23 @@ use {
     termion::is_tty
 };
 
-//TODO !tappedout command
-const COMMANDS: [(&str, usize, fn(&mut ArgsRegular, Vec<String>) -> Result<(), Error>); 1] = [
-    ("all", 0, command_all)
+/// Commands with their numbers of required and optional arguments.
+///
+/// On the command line, optional arguments are only consumed if they start with a digit, so they can't be confused with card names.
+const COMMANDS: [(&str, usize, usize, fn(&mut ArgsRegular, Vec<String>) -> Result<(), Error>); 6] = [
+    ("all", 0, 0, command_all),
+    ("booster", 1, 1, command_booster),
+    ("decklist", 1, 0, command_decklist),
+    ("sealed", 1, 0, command_sealed),
+    ("set", 1, 1, command_set),
+    ("tappedout", 1, 0, command_tappedout)
 ];
 
 //TODO add remaining flags/options from readme
-const FLAGS: [(&str, Option<char>, fn(&mut ArgsRegular) -> Result<(), Error>); 11] = [
+const FLAGS: [(&str, Option<char>, fn(&mut ArgsRegular) -> Result<(), Error>); 19] = [
     ("auto-card-numbers", None, auto_card_numbers),
+    ("crop-images", None, crop_images),
+    ("dfc-checklist", None, dfc_checklist),
     ("holofoil-stamps", None, holofoil_stamps),
     ("include-schemes", None, include_schemes_on),
     ("include-vanguards", None, include_vanguards_on),
@@ -49,22 +80,46 @@ const FLAGS: [(&str, Option<char>, fn(&mut ArgsRegular) -> Result<(), Error>); 1
     ("no-lore-seeker-images", None, no_lore_seeker_images),
     ("no-scryfall-images", None, no_scryfall_images),
     ("offline", None, offline),
-    ("verbose", Some('v'), verbose)
+    ("placeholder-art", None, placeholder_art),
+    ("refresh-db", None, refresh_db),
+    ("refresh-images", None, refresh_images),
+    ("separate-dfc-faces", None, separate_dfc_faces),
+    ("skip-missing", None, skip_missing),
+    ("verbose", Some('v'), verbose),
+    ("with-related", None, with_related_all)
 ];
 
-const OPTIONS: [(&str, Option<char>, fn(&mut ArgsRegular, &str) -> Result<(), Error>); 12] = [
+const OPTIONS: [(&str, Option<char>, fn(&mut ArgsRegular, &str) -> Result<(), Error>); 30] = [
     ("border", Some('b'), border),
     ("copyright", None, copyright),
     ("db", None, database),
+    ("db-max-age", None, db_max_age),
+    ("db-url", None, db_url),
+    ("find-cards", None, find_cards),
+    ("image-cache-max-age", None, image_cache_max_age),
+    ("image-cache-max-size", None, image_cache_max_size),
+    ("image-quality", None, image_quality),
+    ("image-sources", None, image_sources),
     ("images", None, images),
     ("input", Some('i'), input),
+    ("input-format", None, input_format),
+    ("language", None, language),
     ("lore-seeker-hostname", None, lore_seeker_hostname),
     ("lore-seeker-images", None, lore_seeker_images),
+    ("lore-seeker-url", None, lore_seeker_url),
+    ("max-image-size", None, max_image_size),
+    ("meld", None, meld),
     ("output", Some('o'), output),
+    ("quantities", None, quantities),
     ("schemes-output", None, schemes_output),
+    ("scryfall-api-url", None, scryfall_api_url),
     ("scryfall-images", None, scryfall_images),
+    ("seed", None, seed),
     ("set-code", None, set_code),
-    ("vanguards-output", None, vanguards_output)
+    ("tappedout-url", None, tappedout_url),
+    ("text", None, text),
+    ("vanguards-output", None, vanguards_output),
+    ("with-related", None, with_related)
 ];
 
 #[derive(Debug, SmartDefault, Clone)]
@@ -102,34 +157,227 @@ impl Output {
     }
 }
 
+/// How requested quantities of cards are represented in the set file, see `--quantities`.
+#[derive(Debug, SmartDefault, Clone, Copy, PartialEq, Eq)]
+pub enum Quantities {
+    /// Each card is generated once.
+    #[default]
+    Ignore,
+    /// Each card is generated as many times as requested, all copies sharing the same image.
+    Copies,
+    /// Each card is generated once, with the requested quantity in its notes.
+    Notes
+}
+
+impl FromStr for Quantities {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Quantities, Error> {
+        match s {
+            "ignore" => Ok(Quantities::Ignore),
+            "copies" => Ok(Quantities::Copies),
+            "notes" => Ok(Quantities::Notes),
+            _ => Err(Error::Args(format!("unknown value for --quantities: {} (expected ignore, copies, or notes)", s)))
+        }
+    }
+}
+
+/// The languages supported by `--language`, as Scryfall code, MTG JSON name, and Magic Set Editor code.
+const LANGUAGES: [(&str, &str, &str); 11] = [
+    ("en", "English", "EN"),
+    ("de", "German", "DE"),
+    ("es", "Spanish", "ES"),
+    ("fr", "French", "FR"),
+    ("it", "Italian", "IT"),
+    ("ja", "Japanese", "JP"),
+    ("ko", "Korean", "KO"),
+    ("pt", "Portuguese (Brazil)", "PT"),
+    ("ru", "Russian", "RU"),
+    ("zhs", "Chinese Simplified", "CH"),
+    ("zht", "Chinese Traditional", "CH")
+];
+
+/// The language cards are generated in, see `--language`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Language {
+    /// The code used by Scryfall and accepted by `--language`, e.g. `de`.
+    pub code: &'static str,
+    /// The name used in MTG JSON `foreignData`, e.g. `German`.
+    pub mtg_json_name: &'static str,
+    /// The value of the `set language` field in Magic Set Editor, e.g. `DE`.
+    pub mse_code: &'static str
+}
+
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Language, Error> {
+        LANGUAGES.iter()
+            .find(|(code, _, _)| code.eq_ignore_ascii_case(s))
+            .map(|&(code, mtg_json_name, mse_code)| Language { code, mtg_json_name, mse_code })
+            .ok_or_else(|| Error::Args(format!("unknown value for --language: {} (expected one of {})", s, LANGUAGES.iter().map(|(code, _, _)| code).join(", "))))
+    }
+}
+
+/// Where card names, type lines, and rules texts are taken from, see `--text`.
+#[derive(Debug, SmartDefault, Clone, Copy, PartialEq, Eq)]
+pub enum TextSource {
+    /// The current Oracle text.
+    #[default]
+    Oracle,
+    /// The text as printed on the card, falling back to Oracle text where the database doesn't have it.
+    Printed
+}
+
+impl FromStr for TextSource {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<TextSource, Error> {
+        match s {
+            "oracle" => Ok(TextSource::Oracle),
+            "printed" => Ok(TextSource::Printed),
+            _ => Err(Error::Args(format!("unknown value for --text: {} (expected oracle or printed)", s)))
+        }
+    }
+}
+
+/// How the parts of meld pairs are generated, see `--meld`.
+#[derive(Debug, SmartDefault, Clone, Copy, PartialEq, Eq)]
+pub enum MeldMode {
+    /// Each half of the pair is generated as a double-faced card, with the melded card on the back.
+    #[default]
+    Dfc,
+    /// The halves are generated as single-faced cards with meld reminders, and the melded card is generated once using a large meld template.
+    Combined,
+    /// All three parts are generated as single-faced cards with regular templates.
+    Separate
+}
+
+impl FromStr for MeldMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<MeldMode, Error> {
+        match s {
+            "dfc" => Ok(MeldMode::Dfc),
+            "combined" => Ok(MeldMode::Combined),
+            "separate" => Ok(MeldMode::Separate),
+            _ => Err(Error::Args(format!("unknown value for --meld: {} (expected dfc, combined, or separate)", s)))
+        }
+    }
+}
+
+/// A kind of card which is added along with the requested cards, see `--with-related`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Related {
+    /// The other parts of meld pairs.
+    Parts,
+    /// Cards named by “Partner with” abilities.
+    Partners,
+    /// Tokens and emblems created by the card, as listed in the card database.
+    Tokens,
+    /// Other cards mentioned by name in the card's text, e.g. Urza's Mine and Urza's Power Plant for Urza's Tower.
+    Mentioned
+}
+
+const RELATED: [(&str, Related); 4] = [
+    ("parts", Related::Parts),
+    ("partners", Related::Partners),
+    ("tokens", Related::Tokens),
+    ("mentioned", Related::Mentioned)
+];
+
+impl FromStr for Related {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Related, Error> {
+        RELATED.iter()
+            .find(|(name, _)| *name == s)
+            .map(|&(_, related)| related)
+            .ok_or_else(|| Error::Args(format!("unknown value for --with-related: {} (expected none or a comma-separated list of {})", s, RELATED.iter().map(|(name, _)| name).join(", "))))
+    }
+}
+
 #[derive(Debug, SmartDefault, Clone)]
 pub struct ArgsRegular {
     pub all_command: bool,
     pub auto_card_numbers: bool,
+    /// Set codes and numbers of packs given via `!booster` and `!sealed`.
+    pub boosters: Vec<(String, usize)>,
     #[default(Color { r: 222, g: 127, b: 50, a: 1.0 })]
     pub border_color: Color,
-    pub cards: BTreeSet<String>,
+    /// Card names along with how many copies of each were requested.
+    pub cards: BTreeMap<String, usize>,
     #[default = "NOT FOR SALE"]
     pub copyright: String,
-    pub database: Option<PathBuf>,
+    pub crop_images: bool,
+    /// Card databases given via `--db`, with later ones taking precedence. If this is empty, the default database is used.
+    pub database: Vec<DbSource>,
+    /// How long a cached copy of the downloaded card database is used without checking for updates.
+    pub db_max_age: Option<Duration>,
+    /// Where the card database is downloaded from if neither `--db` nor `--offline` is given. This must be a file in the legacy MTG JSON AllSets format.
+    #[default(Url::parse("https://mtgjson.com/json/AllSets.json").expect("failed to parse card database URL"))]
+    pub db_url: Url,
+    pub decklists: Vec<RemoteDecklist>,
+    /// If this is `true`, a substitute card is generated for each double-faced card.
+    pub dfc_checklist: bool,
+    /// Names of cards to leave out even if they're included by a query, decklist, `!all`, `!set`, or `!booster`, see `~` lines.
+    pub excluded_cards: BTreeSet<String>,
+    pub excluded_queries: HashSet<String>,
+    pub find_cards: Option<PathBuf>,
     pub holofoil_stamps: bool,
+    pub image_cache_max_age: Option<Duration>,
+    /// The maximum size of the image cache, in bytes.
+    pub image_cache_max_size: Option<u64>,
+    /// Image file names (without extension) from directories given via `--input`, to be matched with card names once the database is loaded.
+    pub image_names: BTreeSet<String>,
+    /// Additional image providers, e.g. for an in-house art server. These can be referred to by name in `--image-sources`.
+    pub image_providers: Vec<Arc<dyn ImageProvider>>,
+    /// If given, images are re-encoded as JPEG with this quality (1–100).
+    pub image_quality: Option<u8>,
+    /// The names of the image providers to use, in order. If this is `None`, all available providers are used, built-in providers first.
+    pub image_sources: Option<Vec<String>>,
     pub images: Option<PathBuf>,
     include_schemes: Option<bool>,
     include_vanguards: Option<bool>,
-    pub lore_seeker_hostname: Option<String>,
+    /// The format of subsequent `--input` files. If this is `None`, it's detected from the file extension.
+    pub input_format: Option<InputFormat>,
+    /// The language of card names and texts. `None` means English.
+    pub language: Option<Language>,
     pub lore_seeker_images: Option<PathBuf>,
+    pub lore_seeker_url: Option<Url>,
+    /// The maximum width and height of embedded images, in pixels.
+    pub max_image_size: Option<u32>,
+    pub meld: MeldMode,
     pub no_images: bool,
     no_lore_seeker_images: bool,
     no_scryfall_images: bool,
     pub offline: bool,
     pub output: Output,
+    pub placeholder_art: bool,
+    /// Set codes and optional collector number ranges given via `!set`.
+    pub printed_sets: Vec<(String, Option<RangeInclusive<u32>>)>,
+    pub quantities: Quantities,
     pub queries: HashSet<String>,
+    pub refresh_db: bool,
+    pub refresh_images: bool,
     pub schemes_output: Option<Output>,
+    #[default(Url::parse("https://api.scryfall.com/").expect("failed to parse Scryfall API URL"))]
+    pub scryfall_api_url: Url,
     pub scryfall_images: Option<PathBuf>,
+    pub seed: Option<u64>,
+    /// If this is `true`, the faces of double-faced cards are generated as separate single-faced cards.
+    pub separate_dfc_faces: bool,
     #[default = "PROXY"]
     pub set_code: String,
+    /// If this is `true`, cards which aren't found are reported instead of aborting the run.
+    pub skip_missing: bool,
+    #[default(Url::parse("https://tappedout.net/").expect("failed to parse tappedout.net URL"))]
+    pub tappedout_url: Url,
+    pub text: TextSource,
     pub vanguards_output: Option<Output>,
-    pub verbose: bool
+    pub verbose: bool,
+    /// The kinds of related cards to add, see `--with-related`. If this is `None`, only the other parts of meld pairs are added.
+    with_related: Option<BTreeSet<Related>>
 }
 
 impl ArgsRegular {
@@ -179,9 +427,9 @@ impl ArgsRegular {
         } else if line.starts_with('!') {
             let mut args = shlex::split(&line[1..]).ok_or(Error::Args(format!("failed to split !command line")))?;
             let cmd_name = args.remove(0);
-            for &(iter_cmd, num_args, handler) in &COMMANDS {
+            for &(iter_cmd, num_args, num_optional_args, handler) in &COMMANDS {
                 if cmd_name == iter_cmd {
-                    if args.len() != num_args { return Err(Error::Args(format!("wrong number of !{} arguments: expected {}, got {}", cmd_name, num_args, args.len()))); }
+                    if args.len() < num_args || args.len() > num_args + num_optional_args { return Err(Error::Args(format!("wrong number of !{} arguments: expected {}, got {}", cmd_name, expected_args(num_args, num_optional_args), args.len()))); }
                     handler(self, args)?;
                     return Ok(());
                 }
@@ -192,12 +440,33 @@ impl ArgsRegular {
         } else if line.starts_with('=') {
             self.queries.insert(line[1..].to_string());
             Ok(())
+        } else if line.starts_with('~') {
+            self.exclude(&line[1..]);
+            Ok(())
+        } else if decklist::is_section_header(line) {
+            Ok(()) // sideboard etc. are generated along with the main deck
+        } else if let Some((quantity, card_name)) = decklist::parse_text_line(line) {
+            self.add_card(quantity, card_name);
+            Ok(())
         } else {
-            self.cards.insert(line.into());
+            self.add_card(1, line.into());
             Ok(())
         }
     }
 
+    fn add_card(&mut self, quantity: usize, card_name: String) {
+        *self.cards.entry(card_name).or_insert(0) += quantity;
+    }
+
+    /// Handles the part of a `~` line after the `~`, which is either a card name or `=` followed by a query.
+    fn exclude(&mut self, line: &str) {
+        if line.starts_with('=') {
+            self.excluded_queries.insert(line[1..].to_string());
+        } else {
+            self.excluded_cards.insert(line.to_string());
+        }
+    }
+
     pub fn include_schemes(&self) -> bool {
         self.include_schemes.unwrap_or(self.schemes_output.is_none())
     }
@@ -206,10 +475,26 @@ impl ArgsRegular {
         self.include_vanguards.unwrap_or(self.vanguards_output.is_none())
     }
 
+    /// Applies the options which can be set using environment variables, i.e. `MSG_LORE_SEEKER_URL` and `MSG_SCRYFALL_API_URL`.
+    pub fn read_env(&mut self) -> Result<(), Error> {
+        if let Ok(url) = env::var("MSG_LORE_SEEKER_URL") { lore_seeker_url(self, &url)?; }
+        if let Ok(url) = env::var("MSG_SCRYFALL_API_URL") { scryfall_api_url(self, &url)?; }
+        Ok(())
+    }
+
+    /// The base URL of the Lore Seeker instance, `https://lore-seeker.cards/` unless `--lore-seeker-url` is given.
+    pub fn lore_seeker_base_url(&self) -> Url {
+        self.lore_seeker_url.clone().unwrap_or_else(|| Url::parse("https://lore-seeker.cards/").expect("failed to parse Lore Seeker URL"))
+    }
+
     pub(crate) fn no_lore_seeker_images(&self) -> bool {
         self.offline || self.no_lore_seeker_images
     }
 
+    pub fn with_related(&self, related: Related) -> bool {
+        self.with_related.as_ref().map_or(related == Related::Parts, |with_related| with_related.contains(&related))
+    }
+
     pub(crate) fn no_scryfall_images(&self) -> bool {
         self.offline || self.no_scryfall_images
     }
@@ -217,11 +502,20 @@ impl ArgsRegular {
 
 pub enum Args {
     Regular(ArgsRegular),
+    Cache(CacheCommand),
     Help,
     Update,
     Version
 }
 
+/// Subcommands of `msegen cache`.
+#[derive(Debug, Clone, Copy)]
+pub enum CacheCommand {
+    Clear,
+    List,
+    Verify
+}
+
 enum HandleShortArgResult {
     Continue,
     Break,
@@ -230,8 +524,19 @@ enum HandleShortArgResult {
 
 impl Args {
     pub fn new() -> Result<Args, Error> {
-        let mut raw_args = env::args().skip(1);
+        let mut raw_args = env::args().skip(1).peekable();
+        if raw_args.peek().map_or(false, |arg| arg == "cache") {
+            raw_args.next();
+            return Ok(Args::Cache(match (raw_args.next().as_deref(), raw_args.next()) {
+                (Some("clear"), None) => CacheCommand::Clear,
+                (Some("list"), None) => CacheCommand::List,
+                (Some("verify"), None) => CacheCommand::Verify,
+                (_, _) => return Err(Error::Args(format!("usage: msegen cache list|clear|verify")))
+            }));
+        }
         let mut args = ArgsRegular::default();
+        // environment variables are read first so they can be overridden by command-line options
+        args.read_env()?;
         while let Some(arg) = raw_args.next() {
             if arg.starts_with('-') {
                 // no stdin support since pos args aren't paths/files
@@ -262,11 +567,14 @@ impl Args {
             } else if arg.starts_with('!') {
                 let cmd_name = &arg[1..];
                 let mut found = false;
-                for &(iter_cmd, num_args, handler) in &COMMANDS {
+                for &(iter_cmd, num_args, num_optional_args, handler) in &COMMANDS {
                     if cmd_name == iter_cmd {
                         found = true;
-                        let cmd_args = raw_args.by_ref().take(num_args).collect::<Vec<_>>();
-                        if cmd_args.len() != num_args { return Err(Error::Args(format!("wrong number of !{} arguments: expected {}, got {}", cmd_name, num_args, cmd_args.len()))); }
+                        let mut cmd_args = raw_args.by_ref().take(num_args).collect::<Vec<_>>();
+                        if cmd_args.len() != num_args { return Err(Error::Args(format!("wrong number of !{} arguments: expected {}, got {}", cmd_name, expected_args(num_args, num_optional_args), cmd_args.len()))); }
+                        while cmd_args.len() < num_args + num_optional_args && raw_args.peek().map_or(false, |arg| arg.starts_with(|c: char| c.is_ascii_digit())) {
+                            cmd_args.push(raw_args.next().expect("peeked argument is missing"));
+                        }
                         handler(&mut args, cmd_args)?;
                     }
                 }
@@ -277,8 +585,10 @@ impl Args {
                 // comment arg
             } else if arg.starts_with('=') {
                 args.queries.insert(arg[1..].to_string());
+            } else if arg.starts_with('~') {
+                args.exclude(&arg[1..]);
             } else {
-                args.cards.insert(arg);
+                args.add_card(1, arg);
             }
         }
         #[cfg(not(windows))] { //TODO enable for Windows when https://gitlab.redox-os.org/redox-os/termion/issues/167 is fixed
@@ -360,18 +670,93 @@ fn border(args: &mut ArgsRegular, border_color: &str) -> Result<(), Error> {
     Ok(())
 }
 
+fn expected_args(num_args: usize, num_optional_args: usize) -> String {
+    if num_optional_args == 0 {
+        num_args.to_string()
+    } else {
+        format!("{} to {}", num_args, num_args + num_optional_args)
+    }
+}
+
 fn command_all(args: &mut ArgsRegular, _: Vec<String>) -> Result<(), Error> {
     args.all_command = true;
     Ok(())
 }
 
+fn command_booster(args: &mut ArgsRegular, cmd_args: Vec<String>) -> Result<(), Error> {
+    let count = if let Some(count) = cmd_args.get(1) {
+        count.parse().map_err(|e| Error::Args(format!("invalid number of boosters: {}", e)))?
+    } else {
+        1
+    };
+    args.boosters.push((cmd_args[0].clone(), count));
+    Ok(())
+}
+
+fn command_decklist(args: &mut ArgsRegular, cmd_args: Vec<String>) -> Result<(), Error> {
+    args.decklists.push(RemoteDecklist::Url(cmd_args[0].parse()?));
+    Ok(())
+}
+
+fn command_sealed(args: &mut ArgsRegular, cmd_args: Vec<String>) -> Result<(), Error> {
+    args.boosters.push((cmd_args[0].clone(), booster::SEALED_BOOSTERS));
+    Ok(())
+}
+
+fn command_set(args: &mut ArgsRegular, cmd_args: Vec<String>) -> Result<(), Error> {
+    let range = if let Some(range) = cmd_args.get(1) {
+        let (from, to) = match range.split('-').collect_tuple() {
+            Some(range) => range,
+            None => return Err(Error::Args(format!("invalid collector number range for !set: {}", range)))
+        };
+        let from = from.parse::<u32>().map_err(|e| Error::Args(format!("invalid collector number range for !set: {}", e)))?;
+        let to = to.parse::<u32>().map_err(|e| Error::Args(format!("invalid collector number range for !set: {}", e)))?;
+        Some(from..=to)
+    } else {
+        None
+    };
+    args.printed_sets.push((cmd_args[0].clone(), range));
+    Ok(())
+}
+
+fn command_tappedout(args: &mut ArgsRegular, cmd_args: Vec<String>) -> Result<(), Error> {
+    args.decklists.push(RemoteDecklist::TappedOut(cmd_args[0].clone()));
+    Ok(())
+}
+
 fn copyright(args: &mut ArgsRegular, copyright_text: &str) -> Result<(), Error> {
     args.copyright = copyright_text.into();
     Ok(())
 }
 
+fn crop_images(args: &mut ArgsRegular) -> Result<(), Error> {
+    args.crop_images = true;
+    Ok(())
+}
+
 fn database(args: &mut ArgsRegular, db_path: &str) -> Result<(), Error> {
-    args.database = Some(db_path.into());
+    args.database.push(if db_path == "default" { DbSource::Default } else { DbSource::Path(db_path.into()) });
+    Ok(())
+}
+
+fn db_max_age(args: &mut ArgsRegular, hours: &str) -> Result<(), Error> {
+    let hours = hours.parse::<u64>().map_err(|e| Error::Args(format!("invalid value for --db-max-age: {}", e)))?;
+    args.db_max_age = Some(Duration::from_secs(hours * 60 * 60));
+    Ok(())
+}
+
+fn db_url(args: &mut ArgsRegular, url: &str) -> Result<(), Error> {
+    args.db_url = Url::parse(url)?;
+    Ok(())
+}
+
+fn dfc_checklist(args: &mut ArgsRegular) -> Result<(), Error> {
+    args.dfc_checklist = true;
+    Ok(())
+}
+
+fn find_cards(args: &mut ArgsRegular, find_cards_path: &str) -> Result<(), Error> {
+    args.find_cards = Some(find_cards_path.into());
     Ok(())
 }
 
@@ -380,6 +765,31 @@ fn holofoil_stamps(args: &mut ArgsRegular) -> Result<(), Error> {
     Ok(())
 }
 
+fn image_cache_max_age(args: &mut ArgsRegular, days: &str) -> Result<(), Error> {
+    let days = days.parse::<u64>().map_err(|e| Error::Args(format!("invalid value for --image-cache-max-age: {}", e)))?;
+    args.image_cache_max_age = Some(Duration::from_secs(days * 24 * 60 * 60));
+    Ok(())
+}
+
+fn image_cache_max_size(args: &mut ArgsRegular, megabytes: &str) -> Result<(), Error> {
+    let megabytes = megabytes.parse::<u64>().map_err(|e| Error::Args(format!("invalid value for --image-cache-max-size: {}", e)))?;
+    args.image_cache_max_size = Some(megabytes * 1024 * 1024);
+    Ok(())
+}
+
+fn image_quality(args: &mut ArgsRegular, quality: &str) -> Result<(), Error> {
+    match quality.parse::<u8>() {
+        Ok(quality @ 1..=100) => args.image_quality = Some(quality),
+        _ => return Err(Error::Args(format!("invalid value for --image-quality: expected a number from 1 to 100, got {:?}", quality)))
+    }
+    Ok(())
+}
+
+fn image_sources(args: &mut ArgsRegular, sources: &str) -> Result<(), Error> {
+    args.image_sources = Some(sources.split(',').map(str::trim).filter(|source| !source.is_empty()).map(String::from).collect());
+    Ok(())
+}
+
 fn images(args: &mut ArgsRegular, img_dir: &str) -> Result<(), Error> {
     args.images = Some(img_dir.into());
     Ok(())
@@ -406,23 +816,86 @@ fn include_vanguards_on(args: &mut ArgsRegular) -> Result<(), Error> {
 }
 
 fn input(args: &mut ArgsRegular, in_path: &str) -> Result<(), Error> {
-    BufReader::new(File::open(in_path).at(in_path)?)
-        .lines()
-        .map(|line| line.at(in_path).and_then(|line| args.handle_line(line)))
-        .collect::<Result<_, _>>()?;
+    if Path::new(in_path).is_dir() {
+        for entry in fs::read_dir(in_path).at(in_path)? {
+            let path = entry.at(in_path)?.path();
+            let is_image = path.extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| ["png", "jpg", "jpeg"].contains(&&*ext.to_ascii_lowercase()));
+            if is_image {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    args.image_names.insert(stem.to_owned());
+                }
+            }
+        }
+        if args.images.is_none() {
+            args.images = Some(in_path.into());
+        }
+        return Ok(());
+    }
+    match args.input_format.unwrap_or_else(|| InputFormat::from_path(Path::new(in_path))) {
+        InputFormat::Text => {
+            BufReader::new(File::open(in_path).at(in_path)?)
+                .lines()
+                .map(|line| line.at(in_path).and_then(|line| args.handle_line(line)))
+                .collect::<Result<_, _>>()?;
+        }
+        format => for (quantity, card_name) in format.parse(&fs::read_to_string(in_path).at(in_path)?) {
+            args.add_card(quantity, card_name);
+        }
+    }
     Ok(())
 }
 
-fn lore_seeker_hostname(args: &mut ArgsRegular, hostname: &str) -> Result<(), Error> {
-    args.lore_seeker_hostname = Some(hostname.into());
+fn input_format(args: &mut ArgsRegular, format: &str) -> Result<(), Error> {
+    args.input_format = Some(format.parse()?);
     Ok(())
 }
 
+fn base_url(url: &str) -> Result<Url, Error> {
+    let mut url = Url::parse(url)?;
+    if !url.path().ends_with('/') {
+        // make sure relative API paths are joined onto the base URL instead of replacing its last segment
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    Ok(url)
+}
+
+fn language(args: &mut ArgsRegular, code: &str) -> Result<(), Error> {
+    let language = code.parse::<Language>()?;
+    args.language = if language.code == "en" { None } else { Some(language) };
+    Ok(())
+}
+
+fn lore_seeker_hostname(args: &mut ArgsRegular, hostname: &str) -> Result<(), Error> {
+    if hostname.contains("://") {
+        lore_seeker_url(args, hostname)
+    } else {
+        lore_seeker_url(args, &format!("https://{}/", hostname))
+    }
+}
+
 fn lore_seeker_images(args: &mut ArgsRegular, img_dir: &str) -> Result<(), Error> {
     args.lore_seeker_images = Some(img_dir.into());
     Ok(())
 }
 
+fn lore_seeker_url(args: &mut ArgsRegular, url: &str) -> Result<(), Error> {
+    args.lore_seeker_url = Some(base_url(url)?);
+    Ok(())
+}
+
+fn max_image_size(args: &mut ArgsRegular, pixels: &str) -> Result<(), Error> {
+    args.max_image_size = Some(pixels.parse().map_err(|e| Error::Args(format!("invalid value for --max-image-size: {}", e)))?);
+    Ok(())
+}
+
+fn meld(args: &mut ArgsRegular, mode: &str) -> Result<(), Error> {
+    args.meld = mode.parse()?;
+    Ok(())
+}
+
 fn no_images(args: &mut ArgsRegular) -> Result<(), Error> {
     args.no_images = true;
     Ok(())
@@ -448,21 +921,71 @@ fn output(args: &mut ArgsRegular, out_path: &str) -> Result<(), Error> {
     Ok(())
 }
 
+fn placeholder_art(args: &mut ArgsRegular) -> Result<(), Error> {
+    args.placeholder_art = true;
+    Ok(())
+}
+
+fn quantities(args: &mut ArgsRegular, mode: &str) -> Result<(), Error> {
+    args.quantities = mode.parse()?;
+    Ok(())
+}
+
+fn refresh_db(args: &mut ArgsRegular) -> Result<(), Error> {
+    args.refresh_db = true;
+    Ok(())
+}
+
+fn refresh_images(args: &mut ArgsRegular) -> Result<(), Error> {
+    args.refresh_images = true;
+    Ok(())
+}
+
 fn schemes_output(args: &mut ArgsRegular, out_path: &str) -> Result<(), Error> {
     args.schemes_output = Some(out_path.parse()?);
     Ok(())
 }
 
+fn scryfall_api_url(args: &mut ArgsRegular, url: &str) -> Result<(), Error> {
+    args.scryfall_api_url = base_url(url)?;
+    Ok(())
+}
+
 fn scryfall_images(args: &mut ArgsRegular, img_dir: &str) -> Result<(), Error> {
     args.scryfall_images = Some(img_dir.into());
     Ok(())
 }
 
+fn seed(args: &mut ArgsRegular, seed: &str) -> Result<(), Error> {
+    args.seed = Some(seed.parse().map_err(|e| Error::Args(format!("invalid value for --seed: {}", e)))?);
+    Ok(())
+}
+
+fn separate_dfc_faces(args: &mut ArgsRegular) -> Result<(), Error> {
+    args.separate_dfc_faces = true;
+    Ok(())
+}
+
 fn set_code(args: &mut ArgsRegular, set_code: &str) -> Result<(), Error> {
     args.set_code = set_code.into();
     Ok(())
 }
 
+fn skip_missing(args: &mut ArgsRegular) -> Result<(), Error> {
+    args.skip_missing = true;
+    Ok(())
+}
+
+fn tappedout_url(args: &mut ArgsRegular, url: &str) -> Result<(), Error> {
+    args.tappedout_url = base_url(url)?;
+    Ok(())
+}
+
+fn text(args: &mut ArgsRegular, source: &str) -> Result<(), Error> {
+    args.text = source.parse()?;
+    Ok(())
+}
+
 fn vanguards_output(args: &mut ArgsRegular, out_path: &str) -> Result<(), Error> {
     args.vanguards_output = Some(out_path.parse()?);
     Ok(())
@@ -472,3 +995,17 @@ fn verbose(args: &mut ArgsRegular) -> Result<(), Error> {
     args.verbose = true;
     Ok(())
 }
+
+fn with_related(args: &mut ArgsRegular, kinds: &str) -> Result<(), Error> {
+    args.with_related = Some(if kinds == "none" {
+        BTreeSet::default()
+    } else {
+        kinds.split(',').map(str::parse).collect::<Result<_, _>>()?
+    });
+    Ok(())
+}
+
+fn with_related_all(args: &mut ArgsRegular) -> Result<(), Error> {
+    args.with_related = Some(RELATED.iter().map(|&(_, related)| related).collect());
+    Ok(())
+}
@@ -1,7 +1,10 @@
 use {
     std::{
-        cell::RefCell,
-        collections::HashMap,
+        collections::{
+            BTreeSet,
+            HashMap
+        },
+        fmt,
         fs::{
             self,
             File
@@ -18,8 +21,21 @@ use {
             Instant
         }
     },
+    image::{
+        DynamicImage,
+        ImageFormat,
+        ImageOutputFormat,
+        codecs::jpeg::JpegEncoder,
+        imageops::FilterType
+    },
     itertools::Itertools as _,
-    mtg::card::Card,
+    mtg::{
+        card::Card,
+        cardtype::{
+            CardType,
+            EnchantmentType
+        }
+    },
     parking_lot::Mutex,
     reqwest::blocking::{
         Client,
@@ -29,13 +45,49 @@ use {
     url::Url,
     crate::{
         args::ArgsRegular,
+        cache::{
+            ImageCache,
+            PendingDownloads,
+            img_cache,
+            load_index
+        },
+        db::Printing,
+        mse::ModernFrame,
+        placeholder::{
+            self,
+            PlaceholderArt
+        },
+        query,
+        scryfall::ArtIndex,
         util::{
             Error,
             IoResultExt as _
         }
     }
 };
-#[cfg(not(unix))] use directories::ProjectDirs;
+
+/// The names of the image providers built into Magic Set Generator, in their default order.
+pub const BUILTIN_IMAGE_SOURCES: [&str; 3] = ["local", "scryfall", "lore-seeker"];
+
+/// A source of card artwork, such as a local directory or a website.
+///
+/// Providers are queried in the order given by `--image-sources`, and the first one to find an image for a card is used.
+pub trait ImageProvider: fmt::Debug + Send + Sync {
+    /// The name used to refer to this provider in `--image-sources`.
+    fn name(&self) -> &str;
+
+    /// Looks up artwork for the given card, returning `None` if this provider has none.
+    fn find_image(&self, card: &Card) -> Option<Box<dyn ImageSource>>;
+}
+
+/// Artwork found by an [`ImageProvider`].
+pub trait ImageSource: fmt::Debug + Send {
+    /// The artist credit for this image, if known.
+    fn artist(&self) -> Option<String> { None }
+
+    /// Opens the image data so it can be embedded in the set file.
+    fn open(&mut self, card: &Card) -> Result<Box<dyn Read>, Error>;
+}
 
 #[derive(Debug, Deserialize)]
 struct ScryfallData {
@@ -44,6 +96,11 @@ struct ScryfallData {
     image_uris: Option<ScryfallImageUris>
 }
 
+#[derive(Debug, Deserialize)]
+struct ScryfallList {
+    data: Vec<ScryfallData>
+}
+
 #[derive(Debug, Deserialize)]
 struct ScryfallCardFace {
     name: String,
@@ -56,229 +113,451 @@ struct ScryfallImageUris {
 }
 
 #[derive(Debug)]
-enum ImageSource {
-    Path(PathBuf),
-    ScryfallUrl(Url),
-    LoreSeekerUrl {
-        set_code: String,
-        collector_number: String
+struct LocalImages {
+    dirs: Vec<PathBuf>,
+    /// Images in `--images` which were downloaded rather than provided by the user, skipped with `--refresh-images`.
+    downloaded: BTreeSet<String>
+}
+
+impl LocalImages {
+    fn new(args: &ArgsRegular) -> Result<LocalImages, Error> {
+        let mut dirs = vec![args.images.clone()];
+        let mut downloaded = BTreeSet::default();
+        if args.refresh_images {
+            if let Some(ref images) = args.images {
+                downloaded = load_index(images)?.into_iter().map(|(file_name, _)| file_name).collect();
+            }
+        } else {
+            // these directories only contain downloaded images, so they're skipped to force fresh downloads
+            dirs.push(args.scryfall_images.clone());
+            dirs.push(args.lore_seeker_images.clone());
+            if args.images.is_none() && (args.scryfall_images.is_none() || args.lore_seeker_images.is_none()) {
+                dirs.push(img_cache());
+            }
+        }
+        Ok(LocalImages {
+            dirs: dirs.into_iter().filter_map(|dir| dir).collect(),
+            downloaded
+        })
+    }
+}
+
+impl ImageProvider for LocalImages {
+    fn name(&self) -> &str { "local" }
+
+    fn find_image(&self, card: &Card) -> Option<Box<dyn ImageSource>> {
+        for path in &self.dirs {
+            for file_ext in &["png", "PNG", "jpg", "JPG", "jpeg", "JPEG"] {
+                let file_name = format!("{}.{}", normalized_image_name(card), file_ext);
+                if self.downloaded.contains(&file_name) { continue; }
+                let image_path = path.join(file_name);
+                if image_path.exists() {
+                    return Some(Box::new(LocalImage(image_path))); //TODO artist from exif
+                }
+            }
+        }
+        None
     }
 }
 
 #[derive(Debug)]
-pub(crate) struct Image {
-    pub artist: Option<String>,
-    card: Card,
-    pub id: usize,
-    source: ImageSource
+struct LocalImage(PathBuf);
+
+impl ImageSource for LocalImage {
+    fn open(&mut self, _: &Card) -> Result<Box<dyn Read>, Error> {
+        File::open(&self.0)
+            .map(|f| Box::new(f) as Box<dyn Read>)
+            .at(&self.0)
+    }
 }
 
-impl Image {
-    fn path(card: Card, path: PathBuf) -> Image {
-        Image {
-            card,
-            id: 0,
-            artist: None,
-            source: ImageSource::Path(path)
+#[derive(Debug, Clone)]
+struct Scryfall {
+    api_url: Url,
+    client: Client,
+    rate_limit: Arc<Mutex<Option<Instant>>>,
+    save_dir: Option<PathBuf>,
+    downloads: PendingDownloads,
+    /// Set codes of the printings selected using `!set`, so their artwork is used.
+    set_codes: Arc<HashMap<Card, String>>,
+    /// Artwork from Scryfall bulk data, used to skip the API lookup.
+    art_index: Arc<ArtIndex>,
+    /// The Scryfall code of the language given by `--language`, whose printings are preferred.
+    language: Option<&'static str>
+}
+
+impl Scryfall {
+    fn new<'a>(args: &ArgsRegular, client: Client, downloads: PendingDownloads, printings: impl IntoIterator<Item = (&'a Card, &'a Printing)>, art_index: Arc<ArtIndex>) -> Scryfall {
+        Scryfall {
+            api_url: args.scryfall_api_url.clone(),
+            rate_limit: Arc::default(),
+            save_dir: args.scryfall_images.clone().or_else(|| args.images.clone()).or_else(img_cache),
+            set_codes: Arc::new(printings.into_iter().map(|(card, printing)| (card.clone(), printing.set_code.to_lowercase())).collect()),
+            language: args.language.map(|language| language.code),
+            client, downloads, art_index
         }
     }
 
-    fn lore_seeker(card: Card, set_code: &str, collector_number: &str) -> Image {
-        Image {
-            card,
-            id: 0,
-            artist: None,
-            source: ImageSource::LoreSeekerUrl {
-                set_code: set_code.into(),
-                collector_number: collector_number.into()
+    fn request(&self, url: &Url) -> Result<Response, reqwest::Error> {
+        let mut rate_limit = self.rate_limit.lock();
+        if let Some(next_request_time) = *rate_limit {
+            let now = Instant::now();
+            if next_request_time > now {
+                thread::sleep(next_request_time - now);
             }
         }
+        let result = self.client.get(url.as_str()) //TODO remove this url v1 to v2 compat conversion
+            .send()
+            .and_then(|resp| resp.error_for_status());
+        *rate_limit = Some(Instant::now() + Duration::from_millis(100));
+        result
     }
 
-    fn scryfall(card: Card, url: Url, artist: String) -> Image {
-        Image {
-            card,
-            id: 0,
-            artist: Some(artist),
-            source: ImageSource::ScryfallUrl(url)
-        }
+    fn image(&self, card: &Card, scryfall_data: ScryfallData) -> Option<Box<dyn ImageSource>> {
+        let art_crop = if let Some(image_uris) = scryfall_data.image_uris {
+            Some(image_uris.art_crop)
+        } else if let Some(card_faces) = scryfall_data.card_faces {
+            card_faces.into_iter()
+                .filter(|face| face.name == card.to_string())
+                .filter_map(|face| face.image_uris)
+                .collect_tuple()
+                .map(|(image_uris,)| image_uris.art_crop)
+        } else {
+            None
+        }?; //TODO print error if in verbose mode
+        Some(Box::new(ScryfallImage {
+            scryfall: self.clone(),
+            artist: Some(scryfall_data.artist),
+            url: art_crop
+        }))
     }
+}
 
-    fn filename(&self) -> String {
-        normalized_image_name(&self.card)
-    }
+impl ImageProvider for Scryfall {
+    fn name(&self) -> &str { "scryfall" }
 
-    fn open(&mut self, config: &ArtHandlerConfig) -> Result<Box<dyn Read>, Error> {
-        match self.source {
-            ImageSource::Path(ref path) => File::open(path)
-                .map(|f| Box::new(f) as Box<dyn Read>)
-                .at(path),
-            ImageSource::ScryfallUrl(ref url) => {
-                let mut resp = Image::scryfall_download(config, url)?;
-                if let Some(ref img_dir) = config.scryfall_images.as_ref().or(config.images.as_ref()).or(img_cache().as_ref()) {
-                    fs::create_dir_all(img_dir).at(img_dir)?;
-                    let img_path = img_dir.join(format!("{}.png", self.filename()));
-                    io::copy(&mut resp, &mut File::create(&img_path).at(&img_path)?).at(&img_path)?;
-                    //TODO save artist credit in exif data
-                    File::open(&img_path).map(|f| Box::new(f) as Box<dyn Read>).at(img_path)
-                } else {
-                    Ok(Box::new(resp))
-                }
+    fn find_image(&self, card: &Card) -> Option<Box<dyn ImageSource>> {
+        let set_code = self.set_codes.get(&card.primary());
+        if let Some(art_crop) = self.art_index.get(&card.to_string(), set_code.map(String::as_str)) {
+            return Some(Box::new(ScryfallImage {
+                scryfall: self.clone(),
+                artist: art_crop.artist.clone(),
+                url: art_crop.url.clone()
+            }));
+        }
+        if let Some(language) = self.language {
+            let mut query = format!("!\"{}\" lang:{}", card, language);
+            if let Some(set_code) = set_code {
+                query.push_str(&format!(" set:{}", set_code));
             }
-            ImageSource::LoreSeekerUrl { ref set_code, ref collector_number } => {
-                let mut resp = lore_seeker::get(config.lore_seeker_hostname.as_deref(), format!("/art/{}/{}.jpg", set_code, collector_number))
-                    .or_else(|_| lore_seeker::get(config.lore_seeker_hostname.as_deref(), format!("/art/{}/{}.png", set_code, collector_number)))?;
-                if let Some(ref img_dir) = config.lore_seeker_images.as_ref().or(config.images.as_ref()).or(img_cache().as_ref()) {
-                    fs::create_dir_all(img_dir).at(img_dir)?;
-                    let img_path = img_dir.join(format!("{}.jpg", self.filename()));
-                    io::copy(&mut resp, &mut File::create(&img_path).at(&img_path)?).at(&img_path)?;
-                    //TODO save artist credit in exif data, if not already present
-                    File::open(&img_path).map(|f| Box::new(f) as Box<dyn Read>).at(img_path)
-                } else {
-                    Ok(Box::new(resp))
-                }
+            let mut url = self.api_url.join("cards/search").expect("failed to build Scryfall API URL");
+            url.query_pairs_mut()
+                .append_pair("q", &query)
+                .append_pair("unique", "prints");
+            if let Some(image) = self.request(&url).ok()
+                .and_then(|resp| resp.json::<ScryfallList>().ok())
+                .and_then(|list| list.data.into_iter().find_map(|scryfall_data| self.image(card, scryfall_data)))
+            {
+                return Some(image);
             }
         }
+        let mut url = self.api_url.join("cards/named").expect("failed to build Scryfall API URL");
+        url.query_pairs_mut().append_pair("exact", &card.to_string());
+        if let Some(set_code) = set_code {
+            url.query_pairs_mut().append_pair("set", set_code);
+        }
+        let scryfall_data = self.request(&url).ok()?.json::<ScryfallData>().ok()?; //TODO print error if in verbose mode
+        self.image(card, scryfall_data)
     }
+}
 
-    fn scryfall_download(config: &ArtHandlerConfig, url: &Url) -> Result<Box<dyn Read>, Error> {
-        match config.scryfall_request(url) {
-            Ok(resp) => Ok(Box::new(resp) as Box<dyn Read>),
-            Err(e) => Err(Error::from(e))
+#[derive(Debug)]
+struct ScryfallImage {
+    scryfall: Scryfall,
+    artist: Option<String>,
+    url: Url
+}
+
+impl ImageSource for ScryfallImage {
+    fn artist(&self) -> Option<String> { self.artist.clone() }
+
+    fn open(&mut self, card: &Card) -> Result<Box<dyn Read>, Error> {
+        let mut resp = self.scryfall.request(&self.url)?;
+        if let Some(ref img_dir) = self.scryfall.save_dir {
+            fs::create_dir_all(img_dir).at(img_dir)?;
+            let file_name = format!("{}.png", normalized_image_name(card));
+            let img_path = img_dir.join(&file_name);
+            let url = resp.url().clone();
+            io::copy(&mut resp, &mut File::create(&img_path).at(&img_path)?).at(&img_path)?;
+            self.scryfall.downloads.record(img_dir, &file_name, &url);
+            //TODO save artist credit in exif data
+            File::open(&img_path).map(|f| Box::new(f) as Box<dyn Read>).at(img_path)
+        } else {
+            Ok(Box::new(resp))
         }
     }
 }
 
 #[derive(Debug, Clone)]
-struct ArtHandlerConfig {
+struct LoreSeeker {
     client: Client,
-    lore_seeker_hostname: Option<String>,
-    scryfall_rate_limit: RefCell<Option<Instant>>,
-    images: Option<PathBuf>,
-    lore_seeker_images: Option<PathBuf>,
-    no_images: bool,
-    no_lore_seeker_images: bool,
-    no_scryfall_images: bool,
-    scryfall_images: Option<PathBuf>
+    save_dir: Option<PathBuf>,
+    downloads: PendingDownloads,
+    url: Url
 }
 
-impl ArtHandlerConfig {
-    fn scryfall_request(&self, url: &Url) -> Result<Response, reqwest::Error> {
-        if let Some(next_request_time) = *self.scryfall_rate_limit.borrow() {
-            let now = Instant::now();
-            if next_request_time > now {
-                thread::sleep(next_request_time - now);
+impl LoreSeeker {
+    fn new(args: &ArgsRegular, client: Client, downloads: PendingDownloads) -> LoreSeeker {
+        LoreSeeker {
+            save_dir: args.lore_seeker_images.clone().or_else(|| args.images.clone()).or_else(img_cache),
+            url: args.lore_seeker_base_url(),
+            client, downloads
+        }
+    }
+
+    fn request(&self, path: &str) -> Result<Response, Error> {
+        Ok(self.client.get(self.url.join(path)?)
+            .send()?
+            .error_for_status()?
+        )
+    }
+}
+
+impl ImageProvider for LoreSeeker {
+    fn name(&self) -> &str { "lore-seeker" }
+
+    fn find_image(&self, card: &Card) -> Option<Box<dyn ImageSource>> {
+        //TODO print errors if in verbose mode
+        let results = query::lore_seeker(&self.client, &self.url, &format!("!{}", card)).ok()?;
+        let ((_, url),) = results.into_iter().collect_tuple()?;
+        // the base URL may have a path of its own, so only the end of the card URL is matched
+        match url.path_segments()?.collect::<Vec<_>>()[..] {
+            [.., "card", set_code, collector_number] => Some(Box::new(LoreSeekerImage { //TODO get artist from Lore Seeker
+                lore_seeker: self.clone(),
+                set_code: set_code.into(),
+                collector_number: collector_number.into()
+            })),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LoreSeekerImage {
+    lore_seeker: LoreSeeker,
+    set_code: String,
+    collector_number: String
+}
+
+impl ImageSource for LoreSeekerImage {
+    fn open(&mut self, card: &Card) -> Result<Box<dyn Read>, Error> {
+        let mut resp = self.lore_seeker.request(&format!("art/{}/{}.jpg", self.set_code, self.collector_number))
+            .or_else(|_| self.lore_seeker.request(&format!("art/{}/{}.png", self.set_code, self.collector_number)))?;
+        if let Some(ref img_dir) = self.lore_seeker.save_dir {
+            fs::create_dir_all(img_dir).at(img_dir)?;
+            let file_name = format!("{}.jpg", normalized_image_name(card));
+            let img_path = img_dir.join(&file_name);
+            let url = resp.url().clone();
+            io::copy(&mut resp, &mut File::create(&img_path).at(&img_path)?).at(&img_path)?;
+            self.lore_seeker.downloads.record(img_dir, &file_name, &url);
+            //TODO save artist credit in exif data, if not already present
+            File::open(&img_path).map(|f| Box::new(f) as Box<dyn Read>).at(img_path)
+        } else {
+            Ok(Box::new(resp))
+        }
+    }
+}
+
+/// The shape of the art box of the stylesheet a card is rendered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArtBox {
+    Normal,
+    /// Used by planeswalkers and Sagas.
+    Tall,
+    /// Used by planes, phenomena, and the front faces of battles.
+    Wide
+}
+
+impl ArtBox {
+    pub(crate) fn for_card(card: &Card) -> ArtBox {
+        if card.type_line() >= CardType::Plane || card.type_line() >= CardType::Phenomenon || ModernFrame::for_card(card) == Some(ModernFrame::Battle) {
+            ArtBox::Wide
+        } else if card.type_line() >= CardType::Planeswalker || card.type_line() >= EnchantmentType::Saga || card.type_line() >= EnchantmentType::Discovery {
+            ArtBox::Tall
+        } else {
+            ArtBox::Normal
+        }
+    }
+
+    /// Width divided by height, approximately matching the M15 templates.
+    fn aspect_ratio(&self) -> f64 {
+        match self {
+            ArtBox::Normal => 1.37,
+            ArtBox::Tall => 0.76,
+            ArtBox::Wide => 1.8
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Image {
+    art_box: ArtBox,
+    pub artist: Option<String>,
+    card: Card,
+    pub id: usize,
+    source: Box<dyn ImageSource>
+}
+
+/// The quality used to re-encode JPEG images which were cropped or scaled down without `--image-quality`.
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// Processing applied to images before they're embedded in the set file.
+#[derive(Debug, Clone, Copy)]
+struct ImageProcessing {
+    crop: bool,
+    max_size: Option<u32>,
+    quality: Option<u8>
+}
+
+impl ImageProcessing {
+    fn is_noop(&self) -> bool {
+        !self.crop && self.max_size.is_none() && self.quality.is_none()
+    }
+
+    fn apply(&self, mut data: Box<dyn Read>, art_box: ArtBox) -> Result<Box<dyn Read>, Error> {
+        if self.is_noop() { return Ok(data); }
+        let mut buf = Vec::default();
+        data.read_to_end(&mut buf).at_unknown()?;
+        let is_jpeg = image::guess_format(&buf).ok() == Some(ImageFormat::Jpeg);
+        let mut image = image::load_from_memory(&buf)?;
+        if self.crop {
+            let (width, height) = (image.width(), image.height());
+            let target_ratio = art_box.aspect_ratio();
+            let (new_width, new_height) = if f64::from(width) / f64::from(height) > target_ratio {
+                ((f64::from(height) * target_ratio).round() as u32, height)
+            } else {
+                (width, (f64::from(width) / target_ratio).round() as u32)
+            };
+            image = image.crop_imm((width - new_width) / 2, (height - new_height) / 2, new_width, new_height);
+        }
+        if let Some(max_size) = self.max_size {
+            if image.width() > max_size || image.height() > max_size {
+                image = image.resize(max_size, max_size, FilterType::Lanczos3);
             }
         }
-        let result = self.client.get(url.as_str()) //TODO remove this url v1 to v2 compat conversion
-            .send()
-            .and_then(|resp| resp.error_for_status());
-        self.scryfall_rate_limit.replace(Some(Instant::now() + Duration::from_millis(100)));
-        result
+        let mut buf = Vec::default();
+        // JPEG images stay JPEG, since converting them to PNG would make them much larger
+        if let Some(quality) = self.quality.or_else(|| if is_jpeg { Some(DEFAULT_JPEG_QUALITY) } else { None }) {
+            JpegEncoder::new_with_quality(&mut buf, quality).encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))?;
+        } else {
+            image.write_to(&mut buf, ImageOutputFormat::Png)?;
+        }
+        Ok(Box::new(io::Cursor::new(buf)))
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct ArtHandler {
     set_images: HashMap<Card, Arc<Mutex<Image>>>,
-    config: ArtHandlerConfig
+    placeholder_cards: BTreeSet<String>,
+    processing: ImageProcessing,
+    providers: Vec<Arc<dyn ImageProvider>>,
+    downloads: PendingDownloads
 }
 
 impl ArtHandler {
-    pub fn new(args: &ArgsRegular, client: Client) -> ArtHandler {
-        ArtHandler {
-            set_images: HashMap::default(),
-            config: ArtHandlerConfig {
-                client,
-                lore_seeker_hostname: args.lore_seeker_hostname.clone(),
-                scryfall_rate_limit: RefCell::default(),
-                images: args.images.clone(),
-                lore_seeker_images: args.lore_seeker_images.clone(),
-                no_images: args.no_images,
-                no_lore_seeker_images: args.no_lore_seeker_images(),
-                no_scryfall_images: args.no_scryfall_images(),
-                scryfall_images: args.scryfall_images.clone()
+    /// `printings` are the printings selected using `!set`, `!booster`, and `!sealed`, whose artwork is preferred where supported.
+    ///
+    /// `art_index` is used by the Scryfall image source to find artwork without using the Scryfall API.
+    pub fn new<'a>(args: &ArgsRegular, client: Client, printings: impl IntoIterator<Item = (&'a Card, &'a Printing)>, art_index: Arc<ArtIndex>) -> Result<ArtHandler, Error> {
+        if args.image_cache_max_age.is_some() || args.image_cache_max_size.is_some() {
+            if let Some(cache) = ImageCache::new() {
+                cache.evict(args.image_cache_max_age, args.image_cache_max_size)?;
+            }
+        }
+        let downloads = PendingDownloads::default();
+        let mut available = Vec::<Arc<dyn ImageProvider>>::default();
+        if !args.no_images {
+            available.push(Arc::new(LocalImages::new(args)?));
+            if !args.no_scryfall_images() {
+                available.push(Arc::new(Scryfall::new(args, client.clone(), downloads.clone(), printings, art_index)));
+            }
+            if !args.no_lore_seeker_images() {
+                available.push(Arc::new(LoreSeeker::new(args, client, downloads.clone())));
             }
+            available.extend(args.image_providers.iter().cloned());
         }
+        let mut providers = if let Some(ref names) = args.image_sources {
+            let mut providers = Vec::default();
+            for name in names {
+                if let Some(provider) = available.iter().find(|provider| provider.name() == name) {
+                    providers.push(Arc::clone(provider));
+                } else if !BUILTIN_IMAGE_SOURCES.contains(&&name[..]) && !args.image_providers.iter().any(|provider| provider.name() == name) {
+                    return Err(Error::Args(format!("unknown image source: {}", name)));
+                }
+            }
+            providers
+        } else {
+            available
+        };
+        if args.placeholder_art {
+            providers.push(Arc::new(PlaceholderArt));
+        }
+        Ok(ArtHandler {
+            set_images: HashMap::default(),
+            placeholder_cards: BTreeSet::default(),
+            processing: ImageProcessing {
+                crop: args.crop_images,
+                max_size: args.max_image_size,
+                quality: args.image_quality
+            },
+            providers, downloads
+        })
     }
 
-    fn add_image(&mut self, mut image: Image) -> Option<Arc<Mutex<Image>>> {
-        image.id = self.set_images.len() + 1;
-        let card = image.card.clone();
-        let image_arc = Arc::new(Mutex::new(image));
-        self.set_images.insert(card, image_arc.clone());
+    fn add_image(&mut self, card: &Card, art_box: ArtBox, source: Box<dyn ImageSource>) -> Option<Arc<Mutex<Image>>> {
+        let image_arc = Arc::new(Mutex::new(Image {
+            artist: source.artist(),
+            art_box,
+            card: card.clone(),
+            id: self.set_images.len() + 1,
+            source
+        }));
+        self.set_images.insert(card.clone(), image_arc.clone());
         Some(image_arc)
     }
 
     pub(crate) fn open_images(&mut self) -> impl Iterator<Item = Result<(usize, Box<dyn Read>), Error>> + '_ {
-        let config = self.config.clone();
+        let processing = self.processing;
         self.set_images.values().map(move |img| {
             let mut img = img.lock();
-            img.open(&config).map(|f| (img.id, f))
+            let img = &mut *img;
+            let data = img.source.open(&img.card)?;
+            Ok((img.id, processing.apply(data, img.art_box)?))
         })
     }
 
-    pub(crate) fn register_image_for(&mut self, card: &Card) -> Option<Arc<Mutex<Image>>> {
-        if self.config.no_images { return None; }
+    /// Adds the images downloaded by `open_images` to the indexes of the directories they were saved to.
+    pub(crate) fn save_downloads(&self) -> Result<(), Error> {
+        self.downloads.save()
+    }
+
+    pub(crate) fn register_image_for(&mut self, card: &Card, art_box: ArtBox) -> Option<Arc<Mutex<Image>>> {
         if let Some(image) = self.set_images.get(card) { return Some(Arc::clone(image)); }
-        let mut img_dirs = vec![self.config.images.clone(), self.config.scryfall_images.clone(), self.config.lore_seeker_images.clone()];
-        if self.config.images.is_none() && (self.config.scryfall_images.is_none() || self.config.lore_seeker_images.is_none()) {
-            img_dirs.push(img_cache());
-        }
-        for img_dir in img_dirs {
-            if let Some(path) = img_dir {
-                for file_ext in &["png", "PNG", "jpg", "JPG", "jpeg", "JPEG"] {
-                    let image_path = path.join(format!("{}.{}", normalized_image_name(card), file_ext));
-                    if image_path.exists() {
-                        return self.add_image(Image::path(card.clone(), image_path)); //TODO artist from exif
-                    }
-                }
-            }
-        }
-        if !self.config.no_scryfall_images {
-            let mut url = Url::parse("https://api.scryfall.com/cards/named").expect("failed to parse Scryfall API URL");
-            url.query_pairs_mut().append_pair("exact", &card.to_string());
-            if let Ok(resp) = self.config.scryfall_request(&url) {
-                if let Ok(scryfall_data) = resp.json::<ScryfallData>() {
-                    let artist = scryfall_data.artist;
-                    let art_crop = if let Some(image_uris) = scryfall_data.image_uris {
-                        Some(image_uris.art_crop)
-                    } else if let Some(card_faces) = scryfall_data.card_faces {
-                        card_faces.into_iter()
-                            .filter(|face| face.name == card.to_string())
-                            .filter_map(|face| face.image_uris)
-                            .collect_tuple()
-                            .map(|(image_uris,)| image_uris.art_crop)
-                    } else {
-                        None
-                    };
-                    if let Some(art_crop) = art_crop {
-                        return self.add_image(Image::scryfall(card.clone(), art_crop, artist));
-                    } //TODO else print error if in verbose mode
-                } //TODO else print error if in verbose mode
-            }
+        let (provider, source) = self.providers.iter().find_map(|provider| provider.find_image(card).map(|source| (Arc::clone(provider), source)))?;
+        if provider.name() == placeholder::NAME {
+            self.placeholder_cards.insert(card.to_string());
         }
-        if !self.config.no_lore_seeker_images {
-            if let Ok((_, results)) = lore_seeker::resolve_query(self.config.lore_seeker_hostname.as_deref(), &format!("!{}", card)) {
-                if let Some(((_, url),)) = results.into_iter().collect_tuple() {
-                    if let Some(segments) = url.path_segments() {
-                        if let Some(("card", set_code, collector_number)) = segments.collect_tuple() {
-                            return self.add_image(Image::lore_seeker(card.clone(), set_code, collector_number)); //TODO get artist from Lore Seeker
-                        } //TODO else print error if in verbose mode
-                    } //TODO else print error if in verbose mode
-                } //TODO else print error if in verbose mode
-            } //TODO else print error if in verbose mode
-        }
-        None
+        self.add_image(card, art_box, source)
     }
-}
 
-fn img_cache() -> Option<PathBuf> {
-    #[cfg(unix)] { xdg_basedir::get_cache_home().ok().map(|cache_home| cache_home.join("magic-set-generator").join("img")) }
-    #[cfg(not(unix))] { ProjectDirs::from("net", "Fenhl", "Magic Set Generator").map(|proj_dirs| proj_dirs.cache_dir().join("img")) }
+    /// The names of the cards which were given placeholder art because no image was found.
+    pub fn placeholder_cards(&self) -> impl Iterator<Item = &str> + '_ {
+        self.placeholder_cards.iter().map(String::as_str)
+    }
 }
 
-fn normalized_image_name(card: &Card) -> String {
+pub(crate) fn normalized_image_name(card: &Card) -> String {
     let mut card_name = card.to_string();
     card_name.retain(|c| match c {
         ':' | '"' | '?' => false,
@@ -1,15 +1,22 @@
 #![deny(rust_2018_idioms, unused, unused_import_braces, unused_qualifications, warnings)]
 
 use {
-    std::io::{
-        prelude::*,
-        stderr
+    std::{
+        io::{
+            prelude::*,
+            stderr
+        },
+        time::SystemTime
     },
     async_std::task,
     gres::Task as _,
     msegen::{
         Run,
-        args::Args,
+        args::{
+            Args,
+            CacheCommand
+        },
+        cache::ImageCache,
         util::{
             Error,
             IoResultExt as _
@@ -39,6 +46,36 @@ fn main_inner() -> Result<(), Error> {
     let client = msegen::client()?;
     // parse arguments
     let args = match Args::new()? {
+        Args::Cache(cmd) => {
+            let cache = ImageCache::new().ok_or(Error::MissingHomeDir)?;
+            match cmd {
+                CacheCommand::Clear => {
+                    let num_removed = cache.clear()?;
+                    println!("removed {} cached image{}", num_removed, if num_removed == 1 { "" } else { "s" });
+                }
+                CacheCommand::List => {
+                    let now = SystemTime::now();
+                    for image in cache.list()? {
+                        let days = now.duration_since(image.fetched()).map_or(0, |age| age.as_secs() / (24 * 60 * 60));
+                        println!(
+                            "{}\t{} bytes\tfetched {}\t{}",
+                            image.file_name(),
+                            image.size,
+                            match days { 0 => format!("today"), 1 => format!("yesterday"), _ => format!("{} days ago", days) },
+                            image.entry.map_or_else(|| format!("unknown source"), |entry| entry.url.to_string())
+                        );
+                    }
+                }
+                CacheCommand::Verify => {
+                    let broken = cache.verify()?;
+                    for (image, problem) in &broken {
+                        println!("removed {}: {}", image.file_name(), problem);
+                    }
+                    println!("{} broken image{} found in {}", broken.len(), if broken.len() == 1 { "" } else { "s" }, cache.dir().display());
+                }
+            }
+            return Ok(());
+        }
         Args::Help => {
             println!("please see https://github.com/fenhl/magic-set-generator#readme for usage instructions");
             return Ok(());
@@ -75,9 +112,18 @@ fn main_inner() -> Result<(), Error> {
                     Run::LoadDb { updates_available: Some(true), .. } => { eprintln!("\r[ !! ] an update is available, install with `msegen --update`"); }
                     Run::LoadDb { updates_available: Some(false), .. } => { eprintln!("\r[ ok ] Magic Set Generator is up to date"); }
                     Run::NormalizeCardNames { .. } => { verbose_eprint!(args, "[....] normalizing card names"); }
-                    Run::CreateSetMetadata { ref cards, .. } => {
+                    Run::CreateSetMetadata { ref cards, ref pack_cards, seed, ref unmatched_images, ref unmatched_exclusions, .. } => {
                         verbose_eprintln!(args, "\r[ ok ]");
-                        if cards.is_empty() {
+                        for image_name in unmatched_images {
+                            eprintln!("[ !! ] image {:?} doesn't match any card name, skipping", image_name);
+                        }
+                        for card_name in unmatched_exclusions {
+                            verbose_eprintln!(args, "[ !! ] excluded card {:?} doesn't match any card name, ignoring", card_name);
+                        }
+                        if let (Some(seed), None) = (seed, args.seed) {
+                            eprintln!("[ ** ] booster packs were generated with --seed={}", seed);
+                        }
+                        if cards.is_empty() && pack_cards.is_empty() {
                             verbose_eprintln!(args, "[ !! ] no cards specified, generating empty set file");
                         }
                     }
@@ -100,10 +146,20 @@ fn main_inner() -> Result<(), Error> {
                         let progress = 4.min(5 * added_cards / total_cards);
                         verbose_eprint!(args, "[{}{}] adding cards to set file: {} of {}\r", "=".repeat(progress), ".".repeat(4 - progress), added_cards, total_cards);
                     }
-                    Run::GenerateStylesheetSettings { failed, .. } => {
+                    Run::GenerateStylesheetSettings { failed, ref missing_cards, ref art_handler, .. } => {
                         if failed > 0 {
                             eprintln!("[ ** ] {} cards failed. Run again with --verbose for a detailed error message", failed);
                         }
+                        if !missing_cards.is_empty() {
+                            eprintln!("[ ** ] {} card{} not found:", missing_cards.len(), if missing_cards.len() == 1 { " was" } else { "s were" });
+                            for (card_name, suggestions) in missing_cards {
+                                eprintln!("[ !! ] {}", Error::CardNotFound(card_name.clone(), suggestions.clone()));
+                            }
+                        }
+                        let placeholder_cards = art_handler.placeholder_cards().collect::<Vec<_>>();
+                        if !placeholder_cards.is_empty() {
+                            eprintln!("[ ** ] {} card{} got placeholder art: {}", placeholder_cards.len(), if placeholder_cards.len() == 1 { "" } else { "s" }, placeholder_cards.join(", "));
+                        }
                     }
                     Run::WriteMain { .. } => { verbose_eprint!(args, "[....] adding images and saving\r[....]"); }
                     Run::CopyMain { .. } => { verbose_eprint!(args, "\r[=...]"); }
@@ -22,7 +22,6 @@ use {
         executor,
         widget::*
     },
-    itertools::Itertools as _,
     parking_lot::RwLock,
     reqwest::blocking::Client,
     smart_default::SmartDefault,
@@ -32,6 +31,7 @@ use {
             ArgsRegular,
             Output
         },
+        query,
         version::{
             self,
             UpdateProgress
@@ -78,7 +78,7 @@ impl Application for JsonToMse {
 
     fn update(&mut self, message: Message, _: &mut Clipboard) -> Command<Message> {
         match message {
-            Message::Args(msg) => self.args.handle(msg),
+            Message::Args(msg) => self.args.handle(&self.client, msg),
             Message::Done => {
                 self.run = Ok(button::State::default());
                 Command::none()
@@ -88,6 +88,9 @@ impl Application for JsonToMse {
                 Command::none()
             }
             Message::Init => {
+                if let Err(e) = self.args.args.read_env() {
+                    self.run = Err((Percent::MAX, format!("invalid environment variable: {}", e)));
+                }
                 match version::self_update(&self.client) { //TODO make async
                     Ok(Some(new)) => { *self.update_progress.write() = UpdateProgress::RestartToUpdate(new); }
                     Ok(None) => { *self.update_progress.write() = UpdateProgress::NoUpdateAvailable; }
@@ -157,12 +160,12 @@ struct ArgsState {
 }
 
 impl ArgsState {
-    fn handle(&mut self, message: ArgsMessage) -> Command<Message> {
+    fn handle(&mut self, client: &Client, message: ArgsMessage) -> Command<Message> {
         match message {
             ArgsMessage::NewCardNameChange(new_card_name) => { self.new_card_name = new_card_name; }
             ArgsMessage::AddCard => {
                 let new_card_name = mem::take(&mut self.new_card_name);
-                if self.args.cards.insert(new_card_name) {
+                if self.args.cards.insert(new_card_name, 1).is_none() {
                     if let Some(ref mut btns) = self.card_delete_buttons {
                         btns.push(button::State::default());
                     }
@@ -171,10 +174,12 @@ impl ArgsState {
             ArgsMessage::QueryChange(new_query) => { self.query = new_query; }
             ArgsMessage::Search => {
                 let query = mem::take(&mut self.query);
-                match lore_seeker::resolve_query(None, &query) { //TODO async, allow changing Lore Seeker hostname
-                    Ok((_, cards)) => {
+                match query::lore_seeker(client, &self.args.lore_seeker_base_url(), &query) { //TODO async
+                    Ok(cards) => {
                         self.query_error = None;
-                        self.args.cards.extend(cards.into_iter().map(|(card_name, _)| card_name));
+                        for (card_name, _) in cards {
+                            self.args.cards.entry(card_name).or_insert(1);
+                        }
                         if let Some(ref mut btns) = self.card_delete_buttons {
                             btns.resize_with(self.args.cards.len(), button::State::default);
                         }
@@ -187,7 +192,7 @@ impl ArgsState {
             } else {
                 self.args.output = Output::File(PathBuf::from(new_path));
             },
-            ArgsMessage::RemoveCard(card_name) => if self.args.cards.remove(&card_name) {
+            ArgsMessage::RemoveCard(card_name) => if self.args.cards.remove(&card_name).is_some() {
                 if let Some(ref mut btns) = self.card_delete_buttons {
                     btns.pop();
                 }
@@ -210,7 +215,7 @@ impl ArgsState {
                 .push(Button::new(&mut self.show_hide_cards_button, Text::new(if self.card_delete_buttons.is_some() { "Hide" } else { "Show" })).on_press(Message::Args(ArgsMessage::ShowHideCards)))
             );
         if let Some(ref mut del_btns) = self.card_delete_buttons {
-            for (card_name, btn) in self.args.cards.iter().cloned().sorted().zip(del_btns) {
+            for (card_name, btn) in self.args.cards.keys().cloned().zip(del_btns) {
                 col = col.push(Row::new().push(Text::new(card_name.clone())).push(Button::new(btn, Text::new("Remove")).on_press(Message::Args(ArgsMessage::RemoveCard(card_name)))));
             }
         }
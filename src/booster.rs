@@ -0,0 +1,58 @@
+//! Randomized booster packs, see `!booster` and `!sealed`.
+
+use {
+    itertools::Itertools as _,
+    rand::{
+        Rng,
+        seq::SliceRandom as _
+    },
+    crate::db::{
+        Set,
+        SetCard
+    }
+};
+
+/// The number of boosters in a sealed pool.
+pub const SEALED_BOOSTERS: usize = 6;
+
+const COMMONS: usize = 10;
+const UNCOMMONS: usize = 3;
+/// One in this many rare slots contains a mythic rare, if the set has any.
+const MYTHIC_RATE: u32 = 8;
+
+/// Opens a booster pack with 1 rare or mythic rare, 3 uncommons, 10 commons, and 1 basic land.
+///
+/// If the set doesn't have any basic lands, the land slot is replaced with another common. Cards outside the set's base set size, like promos and alternate arts, aren't opened. Cards with multiple faces are only opened once, as their first face.
+///
+/// A pack never contains the same card twice, so if a set has fewer cards of a rarity than there are slots for it, e.g. a small supplemental set, each of them is opened and the pack has fewer cards.
+pub fn open<'a>(set: &'a Set, rng: &mut impl Rng) -> Vec<&'a SetCard> {
+    let cards = set.cards.iter()
+        .filter(|card| set.base_set_size.map_or(true, |base_set_size| card.collector_number_key().0 <= base_set_size))
+        .unique_by(|card| card_number(card))
+        .collect::<Vec<_>>();
+    let with_rarity = |rarity: &'static str| cards.iter()
+        .copied()
+        .filter(move |card| !card.basic_land && card.rarity.as_deref() == Some(rarity))
+        .collect::<Vec<_>>();
+    let commons = with_rarity("common");
+    let uncommons = with_rarity("uncommon");
+    let rares = with_rarity("rare");
+    let mythics = with_rarity("mythic");
+    let lands = cards.iter().copied().filter(|card| card.basic_land).collect::<Vec<_>>();
+    let mut pack = Vec::default();
+    let rare_slot = if !mythics.is_empty() && (rares.is_empty() || rng.gen_ratio(1, MYTHIC_RATE)) { &mythics } else { &rares };
+    pack.extend(rare_slot.choose(rng).copied());
+    pack.extend(uncommons.choose_multiple(rng, UNCOMMONS).copied());
+    if let Some(&land) = lands.choose(rng) {
+        pack.extend(commons.choose_multiple(rng, COMMONS).copied());
+        pack.push(land);
+    } else {
+        pack.extend(commons.choose_multiple(rng, COMMONS + 1).copied());
+    }
+    pack
+}
+
+/// The collector number shared by all faces of a card, e.g. `123` for both `123a` and `123b`.
+fn card_number(card: &SetCard) -> &str {
+    card.number.trim_end_matches(|c: char| c.is_ascii_lowercase())
+}
@@ -0,0 +1,275 @@
+//! Management of the image cache, see `msegen cache`, and the location of the card database cache.
+
+use {
+    std::{
+        collections::BTreeMap,
+        fmt,
+        mem,
+        fs::{
+            self,
+            File
+        },
+        io::{
+            self,
+            SeekFrom,
+            prelude::*
+        },
+        path::{
+            Path,
+            PathBuf
+        },
+        sync::Arc,
+        time::{
+            Duration,
+            SystemTime,
+            UNIX_EPOCH
+        }
+    },
+    parking_lot::Mutex,
+    serde::{
+        Deserialize,
+        Serialize
+    },
+    url::Url,
+    crate::util::{
+        Error,
+        IoResultExt as _,
+        write_atomically
+    }
+};
+#[cfg(not(unix))] use directories::ProjectDirs;
+
+/// The index is stored as a hidden file in the cache directory so it can't collide with any card name.
+const INDEX_FILENAME: &str = ".index.json";
+/// Image files may have trailing data such as metadata after the end marker, so it's searched for in this many bytes at the end of the file.
+const END_MARKER_SEARCH_LEN: u64 = 64 * 1024;
+
+const JPEG_START: &[u8] = b"\xff\xd8\xff";
+const JPEG_END: &[u8] = b"\xff\xd9";
+const PNG_START: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PNG_END: &[u8] = b"IEND\xae\x42\x60\x82";
+
+/// Information about where and when a cached image was downloaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub url: Url,
+    /// The time the image was downloaded, in seconds since the Unix epoch.
+    pub fetched: u64
+}
+
+impl IndexEntry {
+    pub fn fetched(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.fetched)
+    }
+}
+
+#[derive(Debug)]
+pub struct CachedImage {
+    pub path: PathBuf,
+    pub size: u64,
+    /// The index entry for this image. This is `None` for images downloaded by older versions of Magic Set Generator.
+    pub entry: Option<IndexEntry>,
+    modified: SystemTime
+}
+
+impl CachedImage {
+    pub fn file_name(&self) -> String {
+        self.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+    }
+
+    /// The time this image was downloaded, falling back to the file's modification time if it's not in the index.
+    pub fn fetched(&self) -> SystemTime {
+        self.entry.as_ref().map_or(self.modified, IndexEntry::fetched)
+    }
+
+    fn problem(&self) -> Result<Option<Problem>, Error> {
+        if self.size == 0 { return Ok(Some(Problem::Empty)); }
+        let mut file = File::open(&self.path).at(&self.path)?;
+        let mut start = [0; 8];
+        let start_len = read_up_to(&mut file, &mut start).at(&self.path)?;
+        let end_marker = if start[..start_len].starts_with(PNG_START) {
+            PNG_END
+        } else if start[..start_len].starts_with(JPEG_START) {
+            JPEG_END
+        } else {
+            return Ok(Some(Problem::UnknownFormat));
+        };
+        if self.size < (start_len + end_marker.len()) as u64 { return Ok(Some(Problem::Truncated)); }
+        let end_start = self.size.saturating_sub(END_MARKER_SEARCH_LEN).max(start_len as u64);
+        file.seek(SeekFrom::Start(end_start)).at(&self.path)?;
+        let mut end = Vec::default();
+        file.read_to_end(&mut end).at(&self.path)?;
+        Ok(if end.windows(end_marker.len()).any(|window| window == end_marker) { None } else { Some(Problem::Truncated) })
+    }
+}
+
+/// Ways in which a cached image can be broken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    Empty,
+    Truncated,
+    UnknownFormat
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Empty => write!(f, "file is empty"),
+            Problem::Truncated => write!(f, "image data is incomplete"),
+            Problem::UnknownFormat => write!(f, "not a PNG or JPEG image")
+        }
+    }
+}
+
+/// The OS-specific directory where downloaded images are saved by default.
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    dir: PathBuf
+}
+
+impl ImageCache {
+    /// Returns `None` if the OS-specific cache location can't be determined.
+    pub fn new() -> Option<ImageCache> {
+        img_cache().map(|dir| ImageCache { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns all images in the cache, oldest first.
+    pub fn list(&self) -> Result<Vec<CachedImage>, Error> {
+        let mut index = load_index(&self.dir)?;
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::default()),
+            Err(e) => return Err(e.at(&self.dir))
+        };
+        let mut images = Vec::default();
+        for entry in entries {
+            let entry = entry.at(&self.dir)?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if file_name == INDEX_FILENAME { continue; }
+            let metadata = entry.metadata().at(entry.path())?;
+            if !metadata.is_file() { continue; }
+            images.push(CachedImage {
+                size: metadata.len(),
+                entry: index.remove(&file_name),
+                modified: metadata.modified().at(entry.path())?,
+                path: entry.path()
+            });
+        }
+        images.sort_by_key(CachedImage::fetched);
+        Ok(images)
+    }
+
+    fn remove<'a>(&self, images: impl IntoIterator<Item = &'a CachedImage>) -> Result<(), Error> {
+        let mut index = load_index(&self.dir)?;
+        let mut changed = false;
+        for image in images {
+            fs::remove_file(&image.path).at(&image.path)?;
+            changed |= index.remove(&image.file_name()).is_some();
+        }
+        // the index is only rewritten if necessary, so runs which don't remove anything don't overwrite each other's updates
+        if changed { save_index(&self.dir, &index)?; }
+        Ok(())
+    }
+
+    /// Deletes all cached images, returning how many were removed.
+    pub fn clear(&self) -> Result<usize, Error> {
+        let images = self.list()?;
+        self.remove(&images)?;
+        Ok(images.len())
+    }
+
+    /// Deletes cached images which are empty or not valid PNG or JPEG files, and returns them.
+    pub fn verify(&self) -> Result<Vec<(CachedImage, Problem)>, Error> {
+        let mut broken = Vec::default();
+        for image in self.list()? {
+            if let Some(problem) = image.problem()? {
+                broken.push((image, problem));
+            }
+        }
+        self.remove(broken.iter().map(|(image, _)| image))?;
+        Ok(broken)
+    }
+
+    /// Deletes images older than `max_age`, then the oldest remaining images until the cache is no larger than `max_size` bytes. Returns the removed images.
+    pub fn evict(&self, max_age: Option<Duration>, max_size: Option<u64>) -> Result<Vec<CachedImage>, Error> {
+        let now = SystemTime::now();
+        let (mut evicted, mut kept) = self.list()?.into_iter().partition::<Vec<_>, _>(|image| max_age.map_or(false, |max_age| now.duration_since(image.fetched()).map_or(false, |age| age > max_age)));
+        if let Some(max_size) = max_size {
+            let mut total_size = kept.iter().map(|image| image.size).sum::<u64>();
+            while total_size > max_size && !kept.is_empty() {
+                let image = kept.remove(0);
+                total_size -= image.size;
+                evicted.push(image);
+            }
+        }
+        self.remove(&evicted)?;
+        Ok(evicted)
+    }
+}
+
+/// Index entries for images downloaded during this run, which are added to the index of the directory each image was saved to by `save`.
+///
+/// Collecting them first means each index is rewritten once per run rather than once per image.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct PendingDownloads(Arc<Mutex<BTreeMap<PathBuf, BTreeMap<String, IndexEntry>>>>);
+
+impl PendingDownloads {
+    /// Records that the given file in `dir` was just downloaded from `url`.
+    pub(crate) fn record(&self, dir: &Path, file_name: &str, url: &Url) {
+        self.0.lock().entry(dir.to_owned()).or_default().insert(file_name.to_owned(), IndexEntry {
+            url: url.clone(),
+            fetched: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+        });
+    }
+
+    pub(crate) fn save(&self) -> Result<(), Error> {
+        let pending = mem::take(&mut *self.0.lock());
+        for (dir, entries) in pending {
+            let mut index = load_index(&dir)?;
+            index.extend(entries);
+            save_index(&dir, &index)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the index of the images downloaded to the given directory.
+pub(crate) fn load_index(dir: &Path) -> Result<BTreeMap<String, IndexEntry>, Error> {
+    let index_path = dir.join(INDEX_FILENAME);
+    match File::open(&index_path) {
+        Ok(f) => Ok(serde_json::from_reader(f).unwrap_or_default()), // a broken index is discarded rather than making the cache unusable
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::default()),
+        Err(e) => Err(e.at(index_path))
+    }
+}
+
+fn save_index(dir: &Path, index: &BTreeMap<String, IndexEntry>) -> Result<(), Error> {
+    write_atomically(&dir.join(INDEX_FILENAME), |file| Ok(serde_json::to_writer(file, index)?))
+}
+
+pub(crate) fn img_cache() -> Option<PathBuf> {
+    #[cfg(unix)] { xdg_basedir::get_cache_home().ok().map(|cache_home| cache_home.join("magic-set-generator").join("img")) }
+    #[cfg(not(unix))] { ProjectDirs::from("net", "Fenhl", "Magic Set Generator").map(|proj_dirs| proj_dirs.cache_dir().join("img")) }
+}
+
+/// The OS-specific directory where the downloaded card database is cached.
+pub(crate) fn db_cache() -> Option<PathBuf> {
+    #[cfg(unix)] { xdg_basedir::get_cache_home().ok().map(|cache_home| cache_home.join("magic-set-generator").join("db")) }
+    #[cfg(not(unix))] { ProjectDirs::from("net", "Fenhl", "Magic Set Generator").map(|proj_dirs| proj_dirs.cache_dir().join("db")) }
+}
+
+/// Like `read_exact`, but stops early at the end of the file. Returns the number of bytes read.
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n
+        }
+    }
+    Ok(len)
+}
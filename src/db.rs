@@ -0,0 +1,776 @@
+//! Loading the card database, information about sets which isn't exposed by `mtg::card::Db`, and looking up cards by approximate names.
+
+use {
+    std::{
+        collections::{
+            BTreeMap,
+            HashMap,
+            HashSet
+        },
+        fs::{
+            self,
+            File
+        },
+        io::{
+            self,
+            BufReader,
+            BufWriter,
+            prelude::*
+        },
+        ops::RangeInclusive,
+        path::{
+            Path,
+            PathBuf
+        },
+        sync::Arc,
+        time::{
+            Duration,
+            SystemTime,
+            UNIX_EPOCH
+        }
+    },
+    aho_corasick::AhoCorasick,
+    deunicode::deunicode,
+    gitdir::Host as _,
+    itertools::Itertools as _,
+    lazy_static::lazy_static,
+    mtg::card::{
+        Card,
+        Db
+    },
+    regex::Regex,
+    reqwest::{
+        StatusCode,
+        blocking::{
+            Client,
+            Response
+        },
+        header::{
+            ETAG,
+            HeaderName,
+            IF_MODIFIED_SINCE,
+            IF_NONE_MATCH,
+            LAST_MODIFIED
+        }
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+        de::DeserializeOwned
+    },
+    serde_json::{
+        Map,
+        Value,
+        json
+    },
+    url::Url,
+    crate::{
+        args::{
+            ArgsRegular,
+            Related
+        },
+        cache::db_cache,
+        mtg_json,
+        scryfall::{
+            self,
+            ArtIndex
+        },
+        util::{
+            Error,
+            IoResultExt as _,
+            write_atomically
+        }
+    }
+};
+
+lazy_static! {
+    static ref SPLIT_CARD_REGEX: Regex = Regex::new("^(.+?) ?/+ ?.+$").expect("failed to build split card regex");
+}
+
+/// How many of the closest card names are suggested if a card isn't found.
+const NUM_SUGGESTIONS: usize = 3;
+
+/// The downloaded database is cached as CBOR in the form used by `load` (see `AllSets`), which is much faster to read than the original JSON.
+const CACHE_FILENAME: &str = "AllSets.cbor";
+const CACHE_METADATA_FILENAME: &str = "AllSets.meta.json";
+
+/// Information used to check whether the cached card database is up to date.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheMetadata {
+    /// The `--db-url` the database was downloaded from. A cache from a different URL is ignored.
+    #[serde(default)]
+    url: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// The time the database was last downloaded or revalidated, in seconds since the Unix epoch.
+    fetched: u64
+}
+
+impl CacheMetadata {
+    fn new(url: &Url, response: &Response) -> CacheMetadata {
+        let header = |name: HeaderName| response.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_owned);
+        CacheMetadata {
+            url: Some(url.to_string()),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            fetched: now()
+        }
+    }
+
+    /// Returns `None` if there is no cached database, its metadata is unreadable, or it was downloaded from a different URL.
+    fn load(cache_dir: &Path, url: &Url) -> Result<Option<CacheMetadata>, Error> {
+        if !cache_dir.join(CACHE_FILENAME).exists() { return Ok(None); }
+        let metadata_path = cache_dir.join(CACHE_METADATA_FILENAME);
+        Ok(match fs::read(&metadata_path) {
+            Ok(buf) => serde_json::from_slice::<CacheMetadata>(&buf).ok() // broken metadata just causes a new download
+                .filter(|metadata| metadata.url.as_deref() == Some(url.as_str())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.at(metadata_path))
+        })
+    }
+
+    fn save(&self, cache_dir: &Path) -> Result<(), Error> {
+        write_atomically(&cache_dir.join(CACHE_METADATA_FILENAME), |file| Ok(serde_json::to_writer(file, self)?))
+    }
+
+    fn is_fresh(&self, max_age: Option<Duration>) -> bool {
+        max_age.map_or(false, |max_age| now().saturating_sub(self.fetched) <= max_age.as_secs())
+    }
+}
+
+/// A card database in the form used by `load`: `db` is the input for `Db::from_mtg_json`, `sets` is the set information used for `SetIndex`.
+#[derive(Serialize, Deserialize)]
+struct AllSets<D> {
+    db: D,
+    sets: BTreeMap<String, JsonSet>
+}
+
+impl<D: DeserializeOwned> AllSets<D> {
+    /// Reads a database in the legacy MTG JSON AllSets format in a single pass.
+    fn from_json(json: Value) -> Result<AllSets<D>, Error> {
+        #[derive(Deserialize)]
+        struct Flattened<D> {
+            #[serde(flatten)]
+            db: D,
+            #[serde(flatten)]
+            sets: BTreeMap<String, JsonSet>
+        }
+
+        let Flattened { db, sets } = Flattened::deserialize(json)?;
+        Ok(AllSets { db, sets })
+    }
+}
+
+/// One of the card databases combined by `load`.
+enum Layer<D> {
+    /// A database in the legacy MTG JSON AllSets format.
+    Json(Value),
+    /// The downloaded database, which is already deserialized since it may have been read from the cache.
+    Downloaded(AllSets<D>)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonSet {
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "baseSetSize")]
+    base_set_size: Option<u32>,
+    #[serde(default, rename = "releaseDate")]
+    release_date: Option<String>,
+    cards: Vec<JsonCard>,
+    #[serde(default)]
+    tokens: Vec<Value>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonCard {
+    name: String,
+    #[serde(default)]
+    number: String,
+    #[serde(default)]
+    rarity: Option<String>,
+    #[serde(default)]
+    artist: Option<String>,
+    #[serde(default, rename = "type")]
+    type_line: String,
+    #[serde(default, rename = "printedName")]
+    printed_name: Option<String>,
+    #[serde(default, rename = "originalType")]
+    original_type: Option<String>,
+    #[serde(default, rename = "originalText")]
+    original_text: Option<String>,
+    #[serde(default, rename = "foreignData")]
+    foreign_data: Vec<JsonForeignData>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonForeignData {
+    language: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default, rename = "faceName")]
+    face_name: Option<String>,
+    #[serde(default, rename = "type")]
+    type_line: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default, rename = "flavorText")]
+    flavor: Option<String>
+}
+
+/// Text of a card face which replaces its Oracle text, see `--text` and `--language`. Missing fields fall back to the Oracle text.
+#[derive(Debug, Default, Clone)]
+pub struct FaceText {
+    pub name: Option<String>,
+    pub type_line: Option<String>,
+    pub text: Option<String>,
+    pub flavor: Option<String>
+}
+
+impl FaceText {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.type_line.is_none() && self.text.is_none() && self.flavor.is_none()
+    }
+}
+
+/// Replacement texts of card faces, by Oracle face name.
+#[derive(Debug, Default, Clone)]
+pub struct FaceTexts(HashMap<String, FaceText>);
+
+impl FaceTexts {
+    pub fn get(&self, face_name: &str) -> Option<&FaceText> {
+        self.0.get(face_name)
+    }
+
+    pub fn insert(&mut self, face_name: String, text: FaceText) {
+        if !text.is_empty() {
+            self.0.insert(face_name, text);
+        }
+    }
+
+    /// Replaces texts with those from `other`, keeping existing fields which `other` doesn't have.
+    pub fn overlay(&mut self, other: FaceTexts) {
+        for (face_name, text) in other.0 {
+            let existing = self.0.entry(face_name).or_default();
+            existing.name = text.name.or_else(|| existing.name.take());
+            existing.type_line = text.type_line.or_else(|| existing.type_line.take());
+            existing.text = text.text.or_else(|| existing.text.take());
+            existing.flavor = text.flavor.or_else(|| existing.flavor.take());
+        }
+    }
+}
+
+/// A card printing in a set.
+#[derive(Debug, Clone)]
+pub struct SetCard {
+    pub name: String,
+    pub number: String,
+    /// The rarity as given by MTG JSON, e.g. `mythic`.
+    pub rarity: Option<String>,
+    pub artist: Option<String>,
+    pub basic_land: bool,
+    /// The name, type line, and rules text as printed on this card.
+    pub printed: FaceText,
+    /// The texts of this printing in the language given by `--language`, if any.
+    pub localized: FaceText
+}
+
+impl SetCard {
+    /// Sorts collector numbers numerically, with suffixes like the `a` in `123a` as a tiebreaker.
+    pub(crate) fn collector_number_key(&self) -> (u32, &str) {
+        let digits = self.number.find(|c: char| !c.is_ascii_digit()).unwrap_or(self.number.len());
+        (self.number[..digits].parse().unwrap_or(u32::MAX), &self.number[digits..])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Set {
+    pub code: String,
+    pub name: String,
+    /// The number of cards in the set as printed on the cards, excluding promos and other extras. Not available for all sets.
+    pub base_set_size: Option<u32>,
+    /// In `YYYY-MM-DD` format.
+    pub release_date: Option<String>,
+    /// The cards in this set, in the order they appear in the database.
+    pub cards: Vec<SetCard>
+}
+
+impl Set {
+    /// Returns the cards in this set in collector number order, optionally only those whose collector numbers are in the given range.
+    pub fn cards_in_collector_order(&self, range: Option<&RangeInclusive<u32>>) -> Vec<&SetCard> {
+        let mut cards = self.cards.iter()
+            .filter(|card| range.map_or(true, |range| range.contains(&card.collector_number_key().0)))
+            .collect::<Vec<_>>();
+        cards.sort_by(|card1, card2| card1.collector_number_key().cmp(&card2.collector_number_key()));
+        cards
+    }
+}
+
+/// A specific printing of a card, selected using `!set`, `!booster`, or `!sealed`.
+#[derive(Debug, Clone)]
+pub struct Printing {
+    pub set_code: String,
+    pub card: SetCard,
+    /// Where this card appears among all cards selected using these commands, used to generate them in collector number and pack order.
+    pub position: usize,
+    /// The booster pack this card was opened from, numbered from 1.
+    pub pack: Option<usize>
+}
+
+/// The sets in the card database, by set code.
+#[derive(Debug, Default, Clone)]
+pub struct SetIndex {
+    sets: BTreeMap<String, Set>,
+    art_index: Arc<ArtIndex>,
+    /// Tokens and emblems by the names of the cards which create them. Only built if tokens are requested using `--with-related`.
+    tokens: HashMap<String, Vec<Card>>
+}
+
+impl SetIndex {
+    /// Artwork from Scryfall bulk data files given via `--db`. Empty if there are none.
+    pub fn art_index(&self) -> Arc<ArtIndex> {
+        Arc::clone(&self.art_index)
+    }
+
+    /// Only foreign language data for `language`, as named in MTG JSON, is kept.
+    fn insert(&mut self, code: String, set: JsonSet, language: Option<&str>) {
+        let code = code.to_ascii_uppercase();
+        self.sets.insert(code.clone(), Set {
+            name: set.name,
+            base_set_size: set.base_set_size,
+            release_date: set.release_date,
+            cards: set.cards.into_iter().map(|card| SetCard {
+                basic_land: card.type_line.starts_with("Basic") && card.type_line.contains("Land"),
+                printed: FaceText {
+                    name: card.printed_name,
+                    type_line: card.original_type,
+                    text: card.original_text,
+                    flavor: None
+                },
+                localized: language.and_then(|language| card.foreign_data.into_iter().find(|foreign| foreign.language == language)).map(|foreign| FaceText {
+                    name: foreign.face_name.or(foreign.name),
+                    type_line: foreign.type_line,
+                    text: foreign.text,
+                    flavor: foreign.flavor
+                }).unwrap_or_default(),
+                name: card.name,
+                number: card.number,
+                rarity: card.rarity,
+                artist: card.artist
+            }).collect(),
+            code
+        });
+    }
+
+    /// Looks up a set by its code, ignoring case.
+    pub fn get(&self, code: &str) -> Option<&Set> {
+        self.sets.get(&code.to_ascii_uppercase())
+    }
+
+    /// Returns the printed texts of all card faces, each taken from the earliest printing which has any, see `--text printed`.
+    pub fn printed_texts(&self) -> FaceTexts {
+        let mut texts = FaceTexts::default();
+        // newest first, so texts from earlier printings replace them
+        for set in self.sets.values().sorted_by_key(|set| set.release_date.clone().unwrap_or_else(|| format!("9999"))).rev() {
+            for card in &set.cards {
+                texts.insert(card.name.clone(), card.printed.clone());
+            }
+        }
+        texts
+    }
+
+    /// Returns the texts of all card faces in the language given by `--language`, each taken from the newest printing which has any.
+    pub fn localized_texts(&self) -> FaceTexts {
+        let mut texts = FaceTexts::default();
+        // oldest first, so texts from newer printings replace them
+        for set in self.sets.values().sorted_by_key(|set| set.release_date.clone().unwrap_or_default()) {
+            for card in &set.cards {
+                texts.insert(card.name.clone(), card.localized.clone());
+            }
+        }
+        texts
+    }
+
+    /// Returns the tokens and emblems created by the card face with the given name, as listed in the card database.
+    ///
+    /// Each token is returned only once even if it was printed in multiple sets. Tokens which `mtg::card::Db` can't represent are skipped.
+    pub fn tokens_created_by(&self, card_name: &str) -> &[Card] {
+        self.tokens.get(card_name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Checks whether the card with the given name has a printing in the given set.
+    ///
+    /// Split, flip, and adventure cards match by the name of either half as well as the full name.
+    pub fn contains(&self, code: &str, card_name: &str) -> bool {
+        self.get(code).map_or(false, |set| set.cards.iter().any(|card| card.name == card_name || card.name.split(" // ").any(|part| part == card_name)))
+    }
+}
+
+/// Looks up cards by name, tolerating differences in case and diacritics, front face names of multi-faced cards, and Alchemy `A-` prefixes.
+pub struct NameIndex<'a> {
+    db: &'a Db,
+    /// Cards by normalized name, including normalized front face names.
+    cards: HashMap<String, Card>,
+    /// Finds the names of the cards in `mentionable` in rules text.
+    mentions: AhoCorasick,
+    /// The cards with multi-word names, along with their names, see `mentioned_in`.
+    mentionable: Vec<(String, Card)>
+}
+
+impl<'a> NameIndex<'a> {
+    pub fn new(db: &'a Db) -> NameIndex<'a> {
+        let mut cards = HashMap::default();
+        let mut mentionable = Vec::default();
+        for card in db.clone() {
+            let card_name = card.to_string();
+            if let Some(captures) = SPLIT_CARD_REGEX.captures(&card_name) {
+                cards.entry(normalized_card_name(&captures[1])).or_insert_with(|| card.clone());
+            }
+            cards.entry(normalized_card_name(&card_name)).or_insert_with(|| card.clone());
+            if card_name.contains(' ') {
+                mentionable.push((card_name, card));
+            }
+        }
+        NameIndex {
+            mentions: AhoCorasick::new(mentionable.iter().map(|(card_name, _)| card_name)),
+            db, cards, mentionable
+        }
+    }
+
+    /// Returns the card with the given name.
+    ///
+    /// If there's no such card, the error includes the most similar card names as suggestions.
+    pub fn lookup(&self, card_name: &str) -> Result<Card, Error> {
+        let card_name = card_name.trim().replace('’', "'");
+        if let Some(card) = self.db.card(&card_name) { return Ok(card); }
+        let front_name = match SPLIT_CARD_REGEX.captures(&card_name) {
+            Some(captures) => captures[1].to_owned(),
+            None => card_name.clone()
+        };
+        if let Some(card) = self.db.card(&front_name) { return Ok(card); }
+        for name in &[&card_name, &front_name] {
+            let normalized = normalized_card_name(name);
+            if let Some(card) = self.cards.get(&normalized) { return Ok(card.clone()); }
+            // Alchemy rebalanced cards may be missing from the database, so fall back to the original card
+            if let Some(card) = normalized.strip_prefix("a-").and_then(|original| self.cards.get(original)) { return Ok(card.clone()); }
+        }
+        Err(Error::CardNotFound(card_name.clone(), self.suggestions(&card_name)))
+    }
+
+    /// Returns the cards whose names appear as whole words in the given rules text, other than `card` itself.
+    ///
+    /// Only names with multiple words are considered, since single words like “Island” or “Fog” are more often card types or keywords than references to cards.
+    pub fn mentioned_in(&self, text: &str, card: &Card) -> Vec<Card> {
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '\'' || c == '-';
+        self.mentions.find_overlapping_iter(text)
+            .filter(|found| !text[..found.start()].ends_with(is_word_char) && !text[found.end()..].starts_with(is_word_char))
+            .map(|found| &self.mentionable[found.pattern()].1)
+            .filter(|mentioned| *mentioned != card)
+            .unique()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the card names closest to the given name by edit distance.
+    fn suggestions(&self, card_name: &str) -> Vec<String> {
+        let normalized = normalized_card_name(card_name);
+        let max_distance = (normalized.chars().count() / 3).max(2);
+        self.cards.iter()
+            .map(|(name, card)| (strsim::levenshtein(&normalized, name), card.to_string()))
+            .filter(|&(distance, _)| distance <= max_distance)
+            .sorted()
+            .map(|(_, name)| name)
+            .dedup()
+            .take(NUM_SUGGESTIONS)
+            .collect()
+    }
+}
+
+/// Lowercases a card name and replaces characters like `û` and `Æ` with their ASCII equivalents.
+fn normalized_card_name(card_name: &str) -> String {
+    deunicode(card_name).to_lowercase()
+}
+
+/// A card database given via `--db`.
+#[derive(Debug, Clone)]
+pub enum DbSource {
+    /// The database used if `--db` isn't given, i.e. downloaded from MTG JSON or, in `--offline` mode, from Lore Seeker.
+    Default,
+    /// An MTG JSON file, a Scryfall bulk data file, or a directory of MTG JSON set files.
+    Path(PathBuf)
+}
+
+/// Loads the card database as configured by `--db` and `--offline`.
+///
+/// If multiple databases are given, cards from later ones replace cards with the same name from earlier ones.
+pub fn load(client: &Client, args: &ArgsRegular) -> Result<(Db, SetIndex), Error> {
+    let sources = if args.database.is_empty() { vec![DbSource::Default] } else { args.database.clone() };
+    let mut layers = Vec::with_capacity(sources.len());
+    let mut art_index = ArtIndex::default();
+    for source in sources {
+        layers.push(match source {
+            DbSource::Default => if args.offline {
+                Layer::Json(from_sets_dir(&gitdir::GitHub.repo("fenhl/lore-seeker").master()?.join("data").join("sets"), args.verbose)?)
+            } else {
+                Layer::Downloaded(download(client, args)?)
+            },
+            DbSource::Path(db_path) => Layer::Json(if db_path.is_dir() {
+                from_sets_dir(&db_path, args.verbose)?
+            } else {
+                from_file(&db_path, &mut art_index)?
+            })
+        });
+    }
+    let AllSets { db, sets } = if layers.len() == 1 {
+        match layers.remove(0) {
+            Layer::Json(json) => AllSets::from_json(json)?,
+            Layer::Downloaded(all_sets) => all_sets
+        }
+    } else {
+        // the input for Db::from_mtg_json is combined in the AllSets format, the set information separately
+        let mut jsons = Vec::with_capacity(layers.len());
+        let mut set_layers = Vec::with_capacity(layers.len());
+        for layer in layers {
+            match layer {
+                Layer::Json(json) => {
+                    set_layers.push(BTreeMap::<String, JsonSet>::deserialize(&json)?);
+                    jsons.push(json);
+                }
+                Layer::Downloaded(AllSets { db, sets }) => {
+                    jsons.push(serde_json::to_value(db)?);
+                    set_layers.push(sets);
+                }
+            }
+        }
+        AllSets {
+            db: Deserialize::deserialize(layered(jsons))?,
+            sets: layered_sets(set_layers)
+        }
+    };
+    let db = Db::from_mtg_json(db, args.verbose)?;
+    let mut set_index = SetIndex {
+        sets: BTreeMap::default(),
+        art_index: Arc::new(art_index),
+        tokens: HashMap::default()
+    };
+    let mut tokens = Vec::default();
+    for (code, mut set) in sets {
+        tokens.append(&mut set.tokens);
+        set_index.insert(code, set, args.language.map(|language| language.mtg_json_name));
+    }
+    if args.with_related(Related::Tokens) {
+        set_index.tokens = tokens_by_creator(tokens, args.verbose);
+    }
+    Ok((db, set_index))
+}
+
+/// Converts tokens and emblems in the MTG JSON card format to cards, by the names of the cards which create them.
+///
+/// Each token is converted only once even if it was printed in multiple sets. Tokens which `mtg::card::Db` can't represent are skipped, with a warning if `verbose` is set.
+fn tokens_by_creator(tokens: Vec<Value>, verbose: bool) -> HashMap<String, Vec<Card>> {
+    let mut converted = HashMap::<Vec<Option<String>>, Option<Card>>::default();
+    let mut by_creator = HashMap::<String, Vec<Card>>::default();
+    for mut token in tokens {
+        let reverse_related = token.get("reverseRelated")
+            .or_else(|| token.get("relatedCards").and_then(|related| related.get("reverseRelated")))
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).map(str::to_owned).collect::<Vec<_>>())
+            .unwrap_or_default();
+        // printings of the same token only differ in fields like the artist and collector number
+        let key = ["name", "type", "text", "power", "toughness", "colors"].iter().map(|field| token.get(field).map(Value::to_string)).collect::<Vec<_>>();
+        let card = converted.entry(key).or_insert_with(|| {
+            let name = token.get("name").and_then(Value::as_str).unwrap_or_default().to_owned();
+            if let Value::Object(ref mut fields) = token {
+                // mtg::card::Db doesn't know the token and emblem layouts
+                fields.insert(format!("layout"), Value::String(format!("normal")));
+            }
+            let json = json!({ "TOKENS": { "name": "Tokens", "cards": [token] } });
+            match Deserialize::deserialize(&json).map_err(Error::from).and_then(|tokens| Ok(Db::from_mtg_json(tokens, false)?)) {
+                Ok(db) => db.into_iter().next(),
+                Err(e) => {
+                    if verbose {
+                        eprintln!("[ !! ] failed to read token {}, skipping: {}", name, e);
+                    }
+                    None
+                }
+            }
+        });
+        if let Some(card) = card {
+            for card_name in reverse_related {
+                let created = by_creator.entry(card_name).or_default();
+                if !created.contains(card) {
+                    created.push(card.clone());
+                }
+            }
+        }
+    }
+    by_creator
+}
+
+/// Downloads the card database from `--db-url`, unless the cached copy is up to date.
+///
+/// By default, this is the legacy MTG JSON AllSets file, which is no longer updated, so the cached database doesn't include sets released since then.
+///
+/// The cache is used without any requests if it's younger than `--db-max-age`. Otherwise, it's revalidated using its `ETag` and `Last-Modified` headers. `--refresh-db` ignores the cache. If the cache can't be read, the database is downloaded again.
+fn download<D: Serialize + DeserializeOwned>(client: &Client, args: &ArgsRegular) -> Result<AllSets<D>, Error> {
+    let cache_dir = db_cache();
+    let mut metadata = match cache_dir {
+        Some(ref cache_dir) if !args.refresh_db => CacheMetadata::load(cache_dir, &args.db_url)?,
+        _ => None
+    };
+    if let (Some(cache_dir), Some(cached_metadata)) = (&cache_dir, &metadata) {
+        if cached_metadata.is_fresh(args.db_max_age) {
+            if let Ok(all_sets) = from_cache(cache_dir) { return Ok(all_sets); }
+            metadata = None;
+        }
+    }
+    let mut response = request_all_sets(client, &args.db_url, metadata.as_ref())?;
+    if let (Some(cache_dir), Some(mut metadata)) = (&cache_dir, metadata) {
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Ok(all_sets) = from_cache(cache_dir) {
+                metadata.fetched = now();
+                metadata.save(cache_dir)?;
+                return Ok(all_sets);
+            }
+            response = request_all_sets(client, &args.db_url, None)?;
+        }
+    }
+    let new_metadata = CacheMetadata::new(&args.db_url, &response);
+    let all_sets = AllSets::from_json(serde_json::from_slice(&response.bytes()?)?)?;
+    if let Some(cache_dir) = cache_dir {
+        let cache_path = cache_dir.join(CACHE_FILENAME);
+        write_atomically(&cache_path, |file| {
+            let mut writer = BufWriter::new(file);
+            serde_cbor::to_writer(&mut writer, &all_sets)?;
+            writer.flush().at(&cache_path)?;
+            Ok(())
+        })?;
+        // only saved once the cache is complete, so an interrupted download isn't mistaken for an up to date cache
+        new_metadata.save(&cache_dir)?;
+    }
+    Ok(all_sets)
+}
+
+/// Requests the AllSets file, conditional on it having changed since the cached copy described by `metadata` was downloaded.
+fn request_all_sets(client: &Client, url: &Url, metadata: Option<&CacheMetadata>) -> Result<Response, Error> {
+    let mut request = client.get(url.clone())
+        .timeout(Duration::from_secs(600)); // the default timeout is too short for the full database
+    if let Some(metadata) = metadata {
+        if let Some(ref etag) = metadata.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(ref last_modified) = metadata.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    Ok(request.send()?.error_for_status()?)
+}
+
+fn from_cache<D: DeserializeOwned>(cache_dir: &Path) -> Result<AllSets<D>, Error> {
+    let cache_path = cache_dir.join(CACHE_FILENAME);
+    Ok(serde_cbor::from_reader(BufReader::new(File::open(&cache_path).at(&cache_path)?))?)
+}
+
+/// Reads an MTG JSON or Scryfall bulk data file, converting it to the legacy MTG JSON AllSets format.
+///
+/// Artwork from Scryfall bulk data is added to `art_index`.
+fn from_file(path: &Path, art_index: &mut ArtIndex) -> Result<Value, Error> {
+    let json = serde_json::from_reader::<_, Value>(BufReader::new(File::open(path).at(path)?))?;
+    Ok(if scryfall::is_bulk_data(&json) {
+        let (json, file_art_index) = scryfall::convert(json)?;
+        art_index.extend(file_art_index);
+        json
+    } else {
+        mtg_json::to_all_sets(json, path)?
+    })
+}
+
+/// Reads a directory of MTG JSON set files into the AllSets format.
+///
+/// Other JSON files in the directory, like MTG JSON's `Meta.json`, are skipped, with a warning if `verbose` is set.
+fn from_sets_dir(path: &Path, verbose: bool) -> Result<Value, Error> {
+    let mut sets = Map::default();
+    for entry in fs::read_dir(path).at(path)? {
+        let set_path = entry.at(path)?.path();
+        if set_path.extension().map_or(true, |ext| ext != "json") { continue; }
+        let json = serde_json::from_reader::<_, Value>(BufReader::new(File::open(&set_path).at(&set_path)?))?;
+        match mtg_json::to_all_sets(json, &set_path) {
+            Ok(Value::Object(file_sets)) => sets.extend(file_sets),
+            Ok(_) => {}
+            Err(Error::UnsupportedDb(_)) => if verbose {
+                eprintln!("[ !! ] {} is not an MTG JSON set file, skipping", set_path.display());
+            },
+            Err(e) => return Err(e)
+        }
+    }
+    Ok(Value::Object(sets))
+}
+
+/// Combines databases in the AllSets format. Cards replace any cards with the same name from earlier databases, and sets with the same code are merged.
+fn layered(layers: Vec<Value>) -> Value {
+    let mut merged = Map::default();
+    for layer in layers {
+        let layer = match layer {
+            Value::Object(sets) => sets,
+            _ => continue
+        };
+        let names = layer.values()
+            .filter_map(|set| set.get("cards").and_then(Value::as_array))
+            .flatten()
+            .filter_map(|card| card.get("name").and_then(Value::as_str))
+            .map(str::to_owned)
+            .collect::<HashSet<_>>();
+        for set in merged.values_mut() {
+            if let Some(Value::Array(cards)) = set.get_mut("cards") {
+                cards.retain(|card| card.get("name").and_then(Value::as_str).map_or(true, |name| !names.contains(name)));
+            }
+        }
+        for (code, mut set) in layer {
+            if let Some(mut old_set) = merged.remove(&code) {
+                for key in &["cards", "tokens"] {
+                    if let Some(Value::Array(mut cards)) = old_set.get_mut(*key).map(Value::take) {
+                        match set.get_mut(*key) {
+                            Some(Value::Array(new_cards)) => {
+                                cards.append(new_cards);
+                                *new_cards = cards;
+                            }
+                            _ => if let Value::Object(ref mut set) = set {
+                                set.insert(key.to_string(), Value::Array(cards));
+                            }
+                        }
+                    }
+                }
+            }
+            merged.insert(code, set);
+        }
+    }
+    Value::Object(merged)
+}
+
+/// Combines the set information of databases like `layered`.
+fn layered_sets(layers: Vec<BTreeMap<String, JsonSet>>) -> BTreeMap<String, JsonSet> {
+    let mut merged = BTreeMap::<String, JsonSet>::default();
+    for layer in layers {
+        let names = layer.values()
+            .flat_map(|set| &set.cards)
+            .map(|card| card.name.clone())
+            .collect::<HashSet<_>>();
+        for set in merged.values_mut() {
+            set.cards.retain(|card| !names.contains(&card.name));
+        }
+        for (code, mut set) in layer {
+            if let Some(mut old_set) = merged.remove(&code) {
+                old_set.cards.append(&mut set.cards);
+                set.cards = old_set.cards;
+                old_set.tokens.append(&mut set.tokens);
+                set.tokens = old_set.tokens;
+            }
+            merged.insert(code, set);
+        }
+    }
+    merged
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
@@ -0,0 +1,142 @@
+//! Parsers for common decklist file formats, see `--input-format`.
+
+use {
+    std::{
+        path::Path,
+        str::FromStr
+    },
+    lazy_static::lazy_static,
+    regex::Regex,
+    url::Url,
+    crate::{
+        args::ArgsRegular,
+        util::Error
+    }
+};
+
+lazy_static! {
+    static ref TEXT_LINE_REGEX: Regex = Regex::new("^(?:SB:\\s*)?([0-9]{1,3})x?\\s+(.+?)(?:\\s+\\([0-9A-Za-z]{2,6}\\)(?:\\s+\\S+)?)?$").expect("failed to build decklist line regex");
+    static ref MWDECK_LINE_REGEX: Regex = Regex::new("^(?:SB:\\s*)?([0-9]+)\\s+(?:\\[[^\\]]*\\]\\s*)?(.+?)(?:\\s+\\([0-9]+\\))?$").expect("failed to build MWS decklist line regex");
+    static ref XML_ATTR_REGEX: Regex = Regex::new("([A-Za-z_]+)\\s*=\\s*\"([^\"]*)\"").expect("failed to build XML attribute regex");
+    static ref DEK_CARD_REGEX: Regex = Regex::new("<Cards\\s([^>]*)>").expect("failed to build .dek card regex");
+    static ref COD_CARD_REGEX: Regex = Regex::new("<card\\s([^>]*)>").expect("failed to build .cod card regex");
+    static ref COD_TOKENS_REGEX: Regex = Regex::new("(?s)<zone\\s+name=\"tokens\"\\s*>.*?</zone>").expect("failed to build .cod tokens zone regex");
+}
+
+/// Section headers used in plain text and MTG Arena decklists. These are ignored since all sections are generated.
+const SECTION_HEADERS: [&str; 8] = ["about", "commander", "companion", "deck", "main", "mainboard", "maybeboard", "sideboard"];
+
+/// How an `--input` file is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// One card name or special line per line. Also understands plain `4 Lightning Bolt` decklists and MTG Arena exports.
+    Text,
+    /// Magic: The Gathering Online `.dek` XML files.
+    Dek,
+    /// Cockatrice `.cod` XML files.
+    Cod,
+    /// Magic Workstation `.mwDeck` files.
+    MwDeck
+}
+
+impl FromStr for InputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<InputFormat, Error> {
+        match &*s.to_ascii_lowercase() {
+            "text" | "txt" | "arena" => Ok(InputFormat::Text),
+            "dek" | "mtgo" => Ok(InputFormat::Dek),
+            "cod" | "cockatrice" => Ok(InputFormat::Cod),
+            "mwdeck" | "mws" => Ok(InputFormat::MwDeck),
+            _ => Err(Error::Args(format!("unknown input format: {}", s)))
+        }
+    }
+}
+
+impl InputFormat {
+    /// Detects the format from the file extension, defaulting to text.
+    pub fn from_path(path: &Path) -> InputFormat {
+        match path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase()).as_deref() {
+            Some("dek") => InputFormat::Dek,
+            Some("cod") => InputFormat::Cod,
+            Some("mwdeck") => InputFormat::MwDeck,
+            _ => InputFormat::Text
+        }
+    }
+
+    /// Parses the contents of a decklist into quantities and card names.
+    ///
+    /// For text decklists, lines without a quantity are counted as 1 copy, like in `--input` files. Blank lines, `#` and `//` comments, and section headers are skipped. Text `--input` files are instead handled line by line by `ArgsRegular` since they can also contain options, commands, and queries.
+    pub fn parse(&self, text: &str) -> Vec<(usize, String)> {
+        match self {
+            InputFormat::Text => text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("//") && !is_section_header(line))
+                .map(|line| parse_text_line(line).unwrap_or_else(|| (1, line.to_owned())))
+                .collect(),
+            InputFormat::Dek => parse_xml(text, &DEK_CARD_REGEX, "Quantity", "Name"),
+            InputFormat::Cod => parse_xml(&COD_TOKENS_REGEX.replace_all(text, ""), &COD_CARD_REGEX, "number", "name"), // tokens aren't in the card database
+            InputFormat::MwDeck => text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with("//"))
+                .filter_map(|line| MWDECK_LINE_REGEX.captures(line))
+                .filter_map(|captures| Some((captures[1].parse().ok()?, captures[2].to_owned())))
+                .collect()
+        }
+    }
+}
+
+/// A decklist to be downloaded, see the `!tappedout` and `!decklist` commands.
+#[derive(Debug, Clone)]
+pub enum RemoteDecklist {
+    /// A deck ID on tappedout.net, or the site configured with `--tappedout-url`.
+    TappedOut(String),
+    /// A decklist at an arbitrary URL. The format is detected from the file extension, defaulting to text.
+    Url(Url)
+}
+
+impl RemoteDecklist {
+    pub fn url(&self, args: &ArgsRegular) -> Result<Url, Error> {
+        Ok(match self {
+            RemoteDecklist::TappedOut(deck_id) => {
+                let mut url = args.tappedout_url.join(&format!("mtg-decks/{}/", deck_id))?;
+                url.query_pairs_mut().append_pair("fmt", "txt");
+                url
+            }
+            RemoteDecklist::Url(url) => url.clone()
+        })
+    }
+}
+
+/// Parses a line like `4 Lightning Bolt`, `4x Lightning Bolt`, `SB: 1 Naturalize`, or `4 Lightning Bolt (M10) 146`.
+pub(crate) fn parse_text_line(line: &str) -> Option<(usize, String)> {
+    let captures = TEXT_LINE_REGEX.captures(line.trim())?;
+    Some((captures[1].parse().ok()?, captures[2].to_owned()))
+}
+
+pub(crate) fn is_section_header(line: &str) -> bool {
+    SECTION_HEADERS.contains(&&*line.trim().trim_end_matches(':').to_ascii_lowercase())
+}
+
+fn parse_xml(text: &str, element_regex: &Regex, quantity_attr: &str, name_attr: &str) -> Vec<(usize, String)> {
+    element_regex.captures_iter(text).filter_map(|element| {
+        let mut quantity = None;
+        let mut name = None;
+        for attr in XML_ATTR_REGEX.captures_iter(&element[1]) {
+            if &attr[1] == quantity_attr {
+                quantity = attr[2].parse().ok();
+            } else if &attr[1] == name_attr {
+                name = Some(unescape_xml(&attr[2]));
+            }
+        }
+        Some((quantity.unwrap_or(1), name?))
+    }).collect()
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
@@ -2,53 +2,85 @@
 
 pub mod args;
 pub mod art;
+pub mod booster;
+pub mod cache;
+pub mod db;
+pub mod decklist;
 pub mod github;
 pub mod mse;
+mod mtg_json;
+mod placeholder;
+pub mod query;
+mod related;
+pub mod scryfall;
 pub mod util;
 pub mod version;
 
 use {
     std::{
-        collections::BTreeSet,
+        collections::{
+            BTreeMap,
+            BTreeSet,
+            HashMap
+        },
         fmt,
-        fs::File,
         io::{
             self,
             Cursor,
             stdout
         },
+        path::Path,
+        sync::Arc,
         time::Duration
     },
     async_trait::async_trait,
-    gitdir::Host as _,
     gres::{
         Percent,
         Progress,
         Task
     },
-    lazy_static::lazy_static,
+    itertools::Itertools as _,
     mtg::{
         card::{
             Card,
-            Db,
-            Layout
+            Db
         },
         cardtype::CardType
     },
-    regex::Regex,
+    rand::{
+        SeedableRng as _,
+        rngs::StdRng
+    },
     reqwest::blocking::Client,
     crate::{
         args::{
             ArgsRegular,
-            Output
+            Output,
+            TextSource
+        },
+        art::{
+            ArtHandler,
+            normalized_image_name
+        },
+        db::{
+            FaceTexts,
+            NameIndex,
+            Printing,
+            SetIndex
+        },
+        decklist::{
+            InputFormat,
+            RemoteDecklist
         },
-        art::ArtHandler,
         mse::{
             DataFile,
             MseGame
         },
+        query::Query,
+        scryfall::ArtIndex,
         util::{
             Error,
+            IntoResultExt as _,
             IoResultExt as _
         }
     }
@@ -63,10 +95,6 @@ macro_rules! task_try {
     };
 }
 
-lazy_static! {
-    static ref SPLIT_CARD_REGEX: Regex = Regex::new("^(.+?) ?/+ ?.+$").expect("failed to build split card regex");
-}
-
 #[derive(Debug, Clone)]
 pub enum Run {
     NotStarted {
@@ -82,31 +110,63 @@ pub enum Run {
         args: ArgsRegular,
         updates_available: Option<bool>
     },
+    DownloadDecklists {
+        client: Client,
+        args: ArgsRegular,
+        db: Db,
+        sets: SetIndex,
+        decklists: Vec<RemoteDecklist>,
+        cards: BTreeMap<String, usize>
+    },
     ResolveQueries {
         client: Client,
         args: ArgsRegular,
         db: Db,
+        sets: SetIndex,
+        /// All cards in the database, for evaluating queries without an external service, see `Query`. Empty if there are no queries.
+        all_cards: Vec<Card>,
         queries: Vec<String>,
-        cards: BTreeSet<String>
+        excluded_queries: Vec<String>,
+        cards: BTreeMap<String, usize>,
+        excluded_cards: BTreeSet<String>
     },
     NormalizeCardNames {
         client: Client,
         args: ArgsRegular,
         db: Db,
-        cards: BTreeSet<String>
+        sets: SetIndex,
+        cards: BTreeMap<String, usize>,
+        excluded_cards: BTreeSet<String>
     },
     CreateSetMetadata {
         client: Client,
         args: ArgsRegular,
-        cards: BTreeSet<Card>
+        cards: BTreeMap<Card, usize>,
+        printings: BTreeMap<Card, Printing>,
+        /// Cards opened from `!booster` and `!sealed` packs, in pack order.
+        pack_cards: Vec<(Card, Printing)>,
+        /// The random seed used for the booster packs. `None` if no packs were opened.
+        seed: Option<u64>,
+        /// Artwork from Scryfall bulk data files given via `--db`.
+        art_index: Arc<ArtIndex>,
+        /// Texts replacing the Oracle text, see `--text`.
+        texts: FaceTexts,
+        /// Image files from `--input` directories which don't match any card name.
+        unmatched_images: Vec<String>,
+        /// Card names from `~` lines which don't match any card. These are ignored.
+        unmatched_exclusions: Vec<String>,
+        /// Card names which weren't found, with suggestions. Only used with `--skip-missing`.
+        missing_cards: Vec<(String, Vec<String>)>
     },
     AddNextCard {
         client: Client,
         args: ArgsRegular,
-        cards: Vec<Card>,
+        cards: Vec<(Card, usize, Option<Printing>)>,
         added_cards: usize,
         failed: usize,
         error: Option<(String, String, String)>,
+        missing_cards: Vec<(String, Vec<String>)>,
+        texts: FaceTexts,
         art_handler: ArtHandler,
         set_file: DataFile,
         schemes_set_file: DataFile,
@@ -115,6 +175,7 @@ pub enum Run {
     GenerateStylesheetSettings {
         args: ArgsRegular,
         failed: usize,
+        missing_cards: Vec<(String, Vec<String>)>,
         art_handler: ArtHandler,
         set_file: DataFile,
         schemes_set_file: DataFile,
@@ -166,11 +227,12 @@ impl Progress for Run {
             Run::NotStarted { .. } => Percent::default(),
             Run::CheckForUpdates { .. } => Percent::new(1),
             Run::LoadDb { .. } => Percent::new(2),
-            Run::ResolveQueries { .. } => Percent::new(3),
-            Run::NormalizeCardNames { .. } => Percent::new(4),
-            Run::CreateSetMetadata { .. } => Percent::new(5),
+            Run::DownloadDecklists { .. } => Percent::new(3),
+            Run::ResolveQueries { .. } => Percent::new(4),
+            Run::NormalizeCardNames { .. } => Percent::new(5),
+            Run::CreateSetMetadata { .. } => Percent::new(6),
             Run::AddNextCard { added_cards, cards, .. } => {
-                const MIN: u8 = 6; // one above highest value of previous step
+                const MIN: u8 = 7; // one above highest value of previous step
                 const MAX: u8 = 93; // one below lowest value of next step
 
                 let total_cards = added_cards + cards.len();
@@ -193,6 +255,7 @@ impl fmt::Display for Run {
             Run::NotStarted { .. } => write!(f, "not started"),
             Run::CheckForUpdates { .. } => write!(f, "checking for updates"),
             Run::LoadDb { .. } => write!(f, "loading card database"),
+            Run::DownloadDecklists { .. } => write!(f, "downloading decklists"),
             Run::ResolveQueries { .. } => write!(f, "resolving queries"),
             Run::NormalizeCardNames { .. } => write!(f, "normalizing card names"),
             Run::CreateSetMetadata { .. } => write!(f, "generating set metadata"),
@@ -227,93 +290,212 @@ impl Task<Result<(), Error>> for Run {
                 updates_available: Some(task_try!(version::updates_available(&client))),
                 client, args
             }),
-            Run::LoadDb { client, args, .. } => Err(Run::ResolveQueries {
-                db: if let Some(ref db_path) = args.database {
-                    if db_path.is_dir() {
-                        task_try!(Db::from_sets_dir(db_path, args.verbose))
-                    } else {
-                        task_try!(Db::from_mtg_json(task_try!(serde_json::from_reader(task_try!(File::open(db_path).at(db_path)))), args.verbose))
+            Run::LoadDb { client, args, .. } => {
+                let (db, sets) = task_try!(db::load(&client, &args));
+                Err(Run::DownloadDecklists {
+                    cards: args.cards.clone(),
+                    decklists: args.decklists.clone(),
+                    client, args, db, sets
+                })
+            }
+            Run::DownloadDecklists { client, args, db, sets, mut decklists, mut cards } => {
+                if let Some(decklist) = decklists.pop() {
+                    let url = task_try!(decklist.url(&args));
+                    let text = task_try!(client.get(url.clone()).send().and_then(|resp| resp.error_for_status()).and_then(|resp| resp.text())); //TODO async
+                    for (quantity, card_name) in InputFormat::from_path(Path::new(url.path())).parse(&text) {
+                        *cards.entry(card_name).or_insert(0) += quantity;
+                    }
+                }
+                Err(if decklists.is_empty() {
+                    let queries = if args.all_command { Vec::default() } else { args.queries.iter().cloned().collect::<Vec<_>>() };
+                    let excluded_queries = args.excluded_queries.iter().cloned().collect::<Vec<_>>();
+                    Run::ResolveQueries {
+                        all_cards: if queries.is_empty() && excluded_queries.is_empty() { Vec::default() } else { db.clone().into_iter().collect() },
+                        excluded_cards: args.excluded_cards.clone(),
+                        client, args, db, sets, queries, excluded_queries, cards
                     }
-                } else if args.offline {
-                    task_try!(Db::from_sets_dir(task_try!(gitdir::GitHub.repo("fenhl/lore-seeker").master()).join("data").join("sets"), args.verbose))
                 } else {
-                    task_try!(Db::download(args.verbose))
-                },
-                cards: args.cards.clone(),
-                queries: args.queries.iter().cloned().collect(),
-                client, args
-            }),
-            Run::ResolveQueries { client, args, db, mut queries, mut cards } => if args.all_command {
-                Err(Run::CreateSetMetadata {
-                    client, args,
-                    cards: db.into_iter().collect()
+                    Run::DownloadDecklists { client, args, db, sets, decklists, cards }
                 })
-            } else {
+            }
+            Run::ResolveQueries { client, args, db, sets, all_cards, mut queries, mut excluded_queries, mut cards, mut excluded_cards } => {
                 if let Some(query) = queries.pop() {
-                    cards.extend(task_try!(lore_seeker::resolve_query(args.lore_seeker_hostname.as_deref(), &query)).1.into_iter().map(|(card_name, _)| card_name)); //TODO async
+                    for card_name in task_try!(resolve_query(&client, &args, &all_cards, &sets, &query)) {
+                        cards.entry(card_name).or_insert(1);
+                    }
+                } else if let Some(query) = excluded_queries.pop() {
+                    excluded_cards.extend(task_try!(resolve_query(&client, &args, &all_cards, &sets, &query)));
                 }
-                Err(if queries.is_empty() {
-                    Run::NormalizeCardNames { client, args, db, cards }
+                Err(if queries.is_empty() && excluded_queries.is_empty() {
+                    Run::NormalizeCardNames { client, args, db, sets, cards, excluded_cards }
                 } else {
-                    Run::ResolveQueries { client, args, db, queries, cards }
+                    Run::ResolveQueries { client, args, db, sets, all_cards, queries, excluded_queries, cards, excluded_cards }
                 })
-            },
-            Run::NormalizeCardNames { client, args, db, cards } => Err(Run::CreateSetMetadata {
-                cards: if args.all_command { //TODO remove if still not required after adding !tappedout
-                    db.into_iter().collect()
+            }
+            Run::NormalizeCardNames { client, args, db, sets, mut cards, excluded_cards } => {
+                let mut unmatched_images = Vec::default();
+                let mut image_names = Vec::default();
+                for image_name in &args.image_names {
+                    if db.card(image_name).is_some() {
+                        cards.entry(image_name.clone()).or_insert(1);
+                    } else {
+                        image_names.push(image_name);
+                    }
+                }
+                if !image_names.is_empty() {
+                    // image names may have had some characters removed, see art::normalized_image_name
+                    let cards_by_image_name = db.clone().into_iter().map(|card| (normalized_image_name(&card), card.to_string())).collect::<HashMap<_, _>>();
+                    for image_name in image_names {
+                        if let Some(card_name) = cards_by_image_name.get(image_name) {
+                            cards.entry(card_name.clone()).or_insert(1);
+                        } else {
+                            unmatched_images.push(image_name.clone());
+                        }
+                    }
+                }
+                let names = NameIndex::new(&db);
+                let mut missing_cards = Vec::default();
+                let mut lookup = |card_name: String| match names.lookup(&card_name) {
+                    Ok(card) => Ok(Some(card)),
+                    Err(Error::CardNotFound(card_name, suggestions)) if args.skip_missing => {
+                        missing_cards.push((card_name, suggestions));
+                        Ok(None)
+                    }
+                    Err(e) => Err(e)
+                };
+                // excluding a card which doesn't exist has no effect, so these aren't errors
+                let mut unmatched_exclusions = Vec::default();
+                let excluded_cards = excluded_cards.into_iter()
+                    .filter_map(|card_name| match names.lookup(&card_name) {
+                        Ok(card) => Some(card.primary()),
+                        Err(_) => {
+                            unmatched_exclusions.push(card_name);
+                            None
+                        }
+                    })
+                    .collect::<BTreeSet<_>>();
+                let cards = if args.all_command {
+                    db.clone().into_iter().map(|card| (card, 1)).collect::<Vec<_>>()
                 } else {
                     task_try!(cards.into_iter()
-                        //TODO also read card names from args.decklists
-                        .map(|card_name| card_name.replace('’', "'"))
-                        .map(|card_name| match SPLIT_CARD_REGEX.captures(&card_name) {
-                            Some(captures) => captures[1].to_owned(),
-                            None => card_name.to_owned()
+                        .map(|(card_name, quantity)| lookup(card_name).map(|card| card.map(|card| (card, quantity))))
+                        .collect::<Result<Vec<_>, _>>()
+                    ).into_iter().flatten()
+                        // different spellings of the same card, e.g. in the main deck and sideboard, add up
+                        .fold(BTreeMap::default(), |mut cards, (card, quantity)| {
+                            *cards.entry(card).or_insert(0) += quantity;
+                            cards
                         })
-                        .map(|card_name| db.card(&card_name).ok_or_else(|| Error::CardNotFound(card_name)))
-                        .collect::<Result<BTreeSet<_>, _>>()
-                    )
-                }.into_iter()
-                    .flat_map(|card| if let Layout::Meld { top, bottom, .. } = card.layout() {
-                        vec![top, bottom]
-                    } else {
-                        vec![card.primary()]
-                    })
-                    .collect(),
-                client, args
-            }),
-            Run::CreateSetMetadata { client, args, cards } => Err(Run::AddNextCard {
+                        .into_iter().collect::<Vec<_>>()
+                };
+                let mut printings = BTreeMap::default();
+                for (set_code, range) in &args.printed_sets {
+                    let set = task_try!(sets.get(set_code).ok_or_else(|| Error::UnknownSet(set_code.clone())));
+                    for set_card in set.cards_in_collector_order(range.as_ref()) {
+                        // cards which aren't in the database, such as un-cards, are skipped
+                        if let Ok(card) = names.lookup(&set_card.name) {
+                            let position = printings.len();
+                            printings.entry(card.primary()).or_insert_with(|| Printing {
+                                set_code: set.code.clone(),
+                                card: set_card.clone(),
+                                pack: None,
+                                position
+                            });
+                        }
+                    }
+                }
+                let mut pack_cards = Vec::default();
+                let seed = if args.boosters.is_empty() { None } else { Some(args.seed.unwrap_or_else(rand::random)) };
+                if let Some(seed) = seed {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let mut pack_number = 0;
+                    for (set_code, num_packs) in &args.boosters {
+                        let set = task_try!(sets.get(set_code).ok_or_else(|| Error::UnknownSet(set_code.clone())));
+                        for _ in 0..*num_packs {
+                            pack_number += 1;
+                            for set_card in booster::open(set, &mut rng) {
+                                if let Ok(card) = names.lookup(&set_card.name) {
+                                    // excluded cards are left out of the pack rather than replaced, so the same seed still opens the same other cards
+                                    if excluded_cards.contains(&card.primary()) { continue; }
+                                    let position = printings.len() + pack_cards.len();
+                                    pack_cards.push((card.primary(), Printing {
+                                        set_code: set.code.clone(),
+                                        card: set_card.clone(),
+                                        pack: Some(pack_number),
+                                        position
+                                    }));
+                                }
+                            }
+                        }
+                    }
+                }
+                let mut texts = FaceTexts::default();
+                if let TextSource::Printed = args.text {
+                    texts = sets.printed_texts();
+                    // cards from !set, !booster, and !sealed use the text of that printing
+                    for printing in printings.values().chain(pack_cards.iter().map(|(_, printing)| printing)) {
+                        texts.insert(printing.card.name.clone(), printing.card.printed.clone());
+                    }
+                }
+                if args.language.is_some() {
+                    texts.overlay(sets.localized_texts());
+                }
+                let cards = related::expand(cards.into_iter().chain(printings.keys().map(|card| (card.clone(), 1))).collect(), &args, &names, &sets);
+                Err(Run::CreateSetMetadata {
+                    cards: cards.into_iter()
+                        .filter(|(card, _)| !excluded_cards.contains(card))
+                        .fold(BTreeMap::default(), |mut cards, (card, quantity)| {
+                            // a card that's both requested and added by `!set` or as the other half of a meld pair is only counted once
+                            let total = cards.entry(card).or_insert(0);
+                            *total = quantity.max(*total);
+                            cards
+                        }),
+                    art_index: sets.art_index(),
+                    texts,
+                    client, args, printings, pack_cards, seed, unmatched_images, unmatched_exclusions, missing_cards
+                })
+            }
+            Run::CreateSetMetadata { client, args, cards, printings, pack_cards, art_index, texts, missing_cards, .. } => Err(Run::AddNextCard {
                 added_cards: 0,
                 failed: 0,
                 error: None,
-                art_handler: ArtHandler::new(&args, client.clone()),
-                set_file: DataFile::new(&args, cards.len()),
-                schemes_set_file: DataFile::new_schemes(&args, cards.len()),
-                vanguards_set_file: DataFile::new_vanguards(&args, cards.len()),
+                missing_cards, texts,
+                art_handler: task_try!(ArtHandler::new(&args, client.clone(), printings.iter().chain(pack_cards.iter().map(|(card, printing)| (card, printing))), art_index)),
+                set_file: DataFile::new(&args, cards.len() + pack_cards.len()),
+                schemes_set_file: DataFile::new_schemes(&args, cards.len() + pack_cards.len()),
+                vanguards_set_file: DataFile::new_vanguards(&args, cards.len() + pack_cards.len()),
                 client, args,
-                cards: cards.into_iter().collect()
+                cards: cards.into_iter()
+                    .map(|(card, quantity)| {
+                        let printing = printings.get(&card).cloned();
+                        (card, quantity, printing)
+                    })
+                    .chain(pack_cards.into_iter().map(|(card, printing)| (card, 1, Some(printing))))
+                    .sorted_by_key(|(_, _, printing)| printing.as_ref().map_or(usize::MAX, |printing| printing.position)) // cards from !set in collector number order, then booster packs, followed by all others
+                    .collect()
             }),
-            Run::AddNextCard { client, args, mut cards, added_cards, failed, mut art_handler, mut set_file, mut schemes_set_file, mut vanguards_set_file, .. } => {
+            Run::AddNextCard { client, args, mut cards, added_cards, failed, missing_cards, texts, mut art_handler, mut set_file, mut schemes_set_file, mut vanguards_set_file, .. } => {
                 if cards.is_empty() {
-                    Err(Run::GenerateStylesheetSettings { args, failed, art_handler, set_file, schemes_set_file, vanguards_set_file })
+                    Err(Run::GenerateStylesheetSettings { args, failed, missing_cards, art_handler, set_file, schemes_set_file, vanguards_set_file })
                 } else {
-                    let card = cards.remove(0);
+                    let (card, quantity, printing) = cards.remove(0);
                     let result = if card.type_line() >= CardType::Scheme {
                         if args.include_schemes() {
-                            set_file.add_card(&card, MseGame::Magic, &args, &mut art_handler)
+                            set_file.add_card(&card, quantity, printing.as_ref(), MseGame::Magic, &texts, &args, &mut art_handler)
                         } else {
                             Ok(())
-                        }.and_then(|()| schemes_set_file.add_card(&card, MseGame::Archenemy, &args, &mut art_handler))
+                        }.and_then(|()| schemes_set_file.add_card(&card, quantity, printing.as_ref(), MseGame::Archenemy, &texts, &args, &mut art_handler))
                     } else if card.type_line() >= CardType::Vanguard {
                         if args.include_vanguards() {
-                            set_file.add_card(&card, MseGame::Magic, &args, &mut art_handler)
+                            set_file.add_card(&card, quantity, printing.as_ref(), MseGame::Magic, &texts, &args, &mut art_handler)
                         } else {
                             Ok(())
-                        }.and_then(|()| vanguards_set_file.add_card(&card, MseGame::Vanguard, &args, &mut art_handler))
+                        }.and_then(|()| vanguards_set_file.add_card(&card, quantity, printing.as_ref(), MseGame::Vanguard, &texts, &args, &mut art_handler))
                     } else {
-                        set_file.add_card(&card, MseGame::Magic, &args, &mut art_handler)
+                        set_file.add_card(&card, quantity, printing.as_ref(), MseGame::Magic, &texts, &args, &mut art_handler)
                     };
                     Err(Run::AddNextCard {
-                        client, args, cards, art_handler, set_file, schemes_set_file, vanguards_set_file,
+                        client, args, cards, missing_cards, texts, art_handler, set_file, schemes_set_file, vanguards_set_file,
                         added_cards: added_cards + 1,
                         failed: if result.is_ok() { failed } else { failed + 1 },
                         error: result.err().map(|e| (card.to_string(), format!("{:?}", e), e.to_string()))
@@ -369,3 +551,19 @@ pub fn client() -> Result<Client, Error> {
         .build()?
     )
 }
+
+/// Resolves a query using `--find-cards` if given, the built-in query engine if it supports the query, or `find_cards`/Lore Seeker otherwise.
+fn resolve_query(client: &Client, args: &ArgsRegular, all_cards: &[Card], sets: &SetIndex, query: &str) -> Result<Vec<String>, Error> {
+    Ok(if let Some(ref find_cards) = args.find_cards {
+        query::find_cards(find_cards, query)?
+    } else {
+        match query.parse::<Query>() {
+            Ok(parsed) => parsed.resolve(all_cards, sets),
+            Err(e) => if args.offline {
+                query::find_cards(&query::default_find_cards()?, query).annotate(format!("{}, and find_cards failed", e))?
+            } else {
+                query::lore_seeker(client, &args.lore_seeker_base_url(), query)?.into_iter().map(|(card_name, _)| card_name).collect() //TODO async
+            }
+        }
+    })
+}
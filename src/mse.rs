@@ -41,8 +41,19 @@ use {
         write::FileOptions
     },
     crate::{
-        args::ArgsRegular,
-        art::ArtHandler,
+        args::{
+            ArgsRegular,
+            MeldMode,
+            Quantities
+        },
+        art::{
+            ArtBox,
+            ArtHandler
+        },
+        db::{
+            FaceTexts,
+            Printing
+        },
         util::{
             Error,
             IoResultExt as _,
@@ -52,6 +63,9 @@ use {
     }
 };
 
+/// The stylesheet for melded cards with `--meld=combined`, showing the melded card at the size of both halves of the pair.
+const MELD_STYLESHEET: &str = "m15-meld";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MseGame {
     Magic,
@@ -133,7 +147,7 @@ impl DataFile {
                 &version::GIT_COMMIT_HASH[..7]
             ))),
             ("set code", Data::from(&args.set_code[..])),
-            ("set language", Data::from("EN")),
+            ("set language", Data::from(args.language.map_or("EN", |language| language.mse_code))),
             ("mark errors", Data::from("no")),
             ("automatic reminder text", Data::from(String::default())),
             ("automatic card numbers", Data::from(if args.auto_card_numbers { "yes" } else { "no" })),
@@ -166,20 +180,105 @@ impl DataFile {
         DataFile::new_inner(args, num_cards, "vanguard", "MTG JSON card import: Vanguard avatars")
     }
 
-    pub fn add_card(&mut self, card: &Card, mse_game: MseGame, args: &ArgsRegular, art_handler: &mut ArtHandler) -> Result<(), Error> {
-        let card_data = DataFile::from_card(card, mse_game, args, art_handler);
-        if let Some(stylesheet) = card_data.get("stylesheet") {
-            let prefixed_stylesheet = format!("{}-{}", mse_game, stylesheet.render());
-            if !self["styling"].contains(&prefixed_stylesheet) {
-                self["styling"].expect_subfile_mut("found flat set styling data").push(prefixed_stylesheet, set_styling_data(args, &stylesheet.render()));
+    /// `texts` replace the Oracle names, type lines, and rules texts of card faces where present.
+    ///
+    /// Depending on `--separate-dfc-faces` and `--dfc-checklist`, a double-faced card may be added as multiple MSE cards.
+    pub fn add_card(&mut self, card: &Card, quantity: usize, printing: Option<&Printing>, mse_game: MseGame, texts: &FaceTexts, args: &ArgsRegular, art_handler: &mut ArtHandler) -> Result<(), Error> {
+        let mut entries = vec![DataFile::from_card(card, mse_game, texts, args, art_handler)];
+        let mut checklist = None;
+        if let (MseGame::Magic, Layout::DoubleFaced { back, .. }) = (mse_game, card.layout()) {
+            if args.separate_dfc_faces {
+                entries.push(DataFile::from_card(&back, mse_game, texts, args, art_handler));
+            }
+            if args.dfc_checklist {
+                checklist = Some(DataFile::dfc_checklist(card, &back, printing, texts));
+            }
+        }
+        if let Some(printing) = printing {
+            for card_data in &mut entries {
+                card_data.set_printing(printing, true);
+            }
+            if let Some(ref mut checklist) = checklist {
+                checklist.set_printing(printing, false); // the checklist card has no artwork, so it gets no illustrator
+            }
+        }
+        entries.extend(checklist);
+        for card_data in &entries {
+            if let Some(stylesheet) = card_data.get("stylesheet") {
+                let prefixed_stylesheet = format!("{}-{}", mse_game, stylesheet.render());
+                if !self["styling"].contains(&prefixed_stylesheet) {
+                    self["styling"].expect_subfile_mut("found flat set styling data").push(prefixed_stylesheet, set_styling_data(args, &stylesheet.render()));
+                }
+            }
+        }
+        let mut notes = Vec::default();
+        if let Some(pack) = printing.and_then(|printing| printing.pack) {
+            notes.push(format!("pack {}", pack));
+        }
+        if let Quantities::Notes = args.quantities {
+            notes.push(format!("{} {}", quantity, if quantity == 1 { "copy" } else { "copies" }));
+        }
+        for mut card_data in entries {
+            if !notes.is_empty() {
+                card_data.push("notes", notes.join(", "));
+            }
+            match args.quantities {
+                Quantities::Ignore | Quantities::Notes => { self.push("card", card_data); }
+                Quantities::Copies => for _ in 0..quantity {
+                    self.push("card", card_data.clone()); // all copies refer to the same image
+                }
             }
         }
-        self.push("card", card_data);
         Ok(())
     }
 
-    fn from_card(card: &Card, mse_game: MseGame, args: &ArgsRegular, art_handler: &mut ArtHandler) -> DataFile {
-        let alt = card.is_alt();
+    /// Sets the fields that depend on the card's printing rather than the card itself.
+    fn set_printing(&mut self, printing: &Printing, with_illustrator: bool) {
+        self.set("card code text", &printing.card.number);
+        if let Some(rarity) = printing.card.rarity.as_deref().and_then(mse_rarity) {
+            for key in &["rarity", "rarity 2"] {
+                if self.contains(key) { self.set(key, rarity); }
+            }
+        }
+        if with_illustrator {
+            if let Some(ref artist) = printing.card.artist {
+                self.set("illustrator", artist);
+            }
+        }
+    }
+
+    /// A substitute card for a double-faced card, see `--dfc-checklist`. It can be put in a deck instead of the double-faced card, which is kept aside until it's needed.
+    fn dfc_checklist(front: &Card, back: &Card, printing: Option<&Printing>, texts: &FaceTexts) -> DataFile {
+        let name = |face: &Card| texts.get(&face.to_string()).and_then(|face_text| face_text.name.clone()).unwrap_or_else(|| face.to_string());
+        let mut result = DataFile::default();
+        result.push("name", format!("{} // {}", name(front), name(back)));
+        if let Some(mana_cost) = front.mana_cost() {
+            result.push("casting cost", cost_to_mse(mana_cost));
+        }
+        result.push("super type", "Substitute Card");
+        let mut lines = vec![
+            format!("This card represents a double-faced card."),
+            format!("Front: {} ({})", name(front), front.type_line()),
+            format!("Back: {} ({})", name(back), back.type_line()),
+            format!("Mana value: {}", mana_value(front))
+        ];
+        if let Some(printing) = printing {
+            lines.push(format!("Reference: {} #{}", printing.set_code, printing.card.number));
+        }
+        result.push("rule text", lines.join("\n"));
+        result
+    }
+
+    fn from_card(card: &Card, mse_game: MseGame, texts: &FaceTexts, args: &ArgsRegular, art_handler: &mut ArtHandler) -> DataFile {
+        let meld_parts = match card.layout() {
+            Layout::Meld { top, bottom, back } if args.meld != MeldMode::Dfc => Some((top, bottom, back)),
+            _ => None
+        };
+        // with --meld=combined, --meld=separate, or --separate-dfc-faces, back faces are generated on their own
+        let separate_faces = meld_parts.is_some() || args.separate_dfc_faces && matches!(card.layout(), Layout::DoubleFaced { .. });
+        let alt = card.is_alt() && !separate_faces;
+        let frame = ModernFrame::for_card(card);
+        let face_text = texts.get(&card.to_string());
         let mut result = DataFile::default();
 
         macro_rules! push_alt {
@@ -203,23 +302,24 @@ impl DataFile {
         if mse_game == MseGame::Magic {
             match card.layout() {
                 Layout::Normal => {} // nothing specific to normal layout
+                Layout::DoubleFaced { .. } | Layout::Meld { .. } if separate_faces => {} // faces are generated as separate cards
                 Layout::Split { right: alt_part, .. } |
                 Layout::Flip { flipped: alt_part, .. } |
                 Layout::DoubleFaced { back: alt_part, .. } |
                 Layout::Meld { back: alt_part, .. } |
                 Layout::Adventure { adventure: alt_part, .. } => if !alt {
-                    result += DataFile::from_card(&alt_part, mse_game, args, art_handler);
+                    result += DataFile::from_card(&alt_part, mse_game, texts, args, art_handler);
                 }
             }
         }
         // name
-        push_alt!("name", card.to_string());
+        push_alt!("name", face_text.and_then(|face_text| face_text.name.clone()).unwrap_or_else(|| card.to_string()));
         // mana cost
         if let Some(mana_cost) = card.mana_cost() {
             push_alt!("casting cost", cost_to_mse(mana_cost));
         }
         // image
-        if let Some(image) = art_handler.register_image_for(card) {
+        if let Some(image) = art_handler.register_image_for(card, ArtBox::for_card(card)) {
             let image = image.lock();
             push_alt!("image", format!("image{}", image.id));
             if let Some(ref artist) = image.artist {
@@ -231,7 +331,19 @@ impl DataFile {
             push_alt!("indicator", indicator.canonical_order().into_iter().join(", "));
         }
         // type line
-        if mse_game == MseGame::Archenemy {
+        if let Some(type_line) = face_text.and_then(|face_text| face_text.type_line.as_ref()) {
+            if mse_game == MseGame::Archenemy {
+                push_alt!("type", type_line);
+            } else {
+                // printed type lines may use types which no longer exist, like “Summon”, so they're not split into word lists
+                let (types, subtypes) = match type_line.split_once(" — ") {
+                    Some((types, subtypes)) => (types, subtypes),
+                    None => (&type_line[..], "")
+                };
+                push_alt!(if mse_game == MseGame::Vanguard { "type" } else { "super type" }, types);
+                push_alt!("sub type", subtypes);
+            }
+        } else if mse_game == MseGame::Archenemy {
             // Archenemy templates don't have a separate subtypes field, so include them with the card types
             push_alt!("type", card.type_line());
         } else {
@@ -313,7 +425,10 @@ impl DataFile {
                 }
             }
             if let Some(ref separated_text_boxes) = separated_text_boxes {
-                for (i, text_box) in separated_text_boxes.iter().enumerate() {
+                // replacement texts are used if they can be split into the same text boxes
+                let replacement = face_text.and_then(|face_text| face_text.text.as_ref())
+                    .and_then(|text| replacement_text_boxes(text, card.is_leveler(), separated_text_boxes.len()));
+                for (i, text_box) in replacement.as_ref().unwrap_or(separated_text_boxes).iter().enumerate() {
                     result.push(
                         if i == 0 && card.is_leveler() {
                             format!("rule text")
@@ -323,10 +438,30 @@ impl DataFile {
                         text_box
                     );
                 }
+            } else if let Some(text) = face_text.and_then(|face_text| face_text.text.as_ref()) {
+                push_alt!("rule text", with_mse_symbols(text));
             } else {
                 push_alt!("rule text", ability_lines(&abilities).join("\n"));
             }
         }
+        if let Some(frame) = frame {
+            if mse_game == MseGame::Magic && !alt {
+                result.split_text_boxes(frame, &abilities);
+            }
+        }
+        if let (MeldMode::Combined, Some((top, bottom, back))) = (args.meld, &meld_parts) {
+            if card != back {
+                // the halves are single-faced, so remind which card completes the pair
+                let reminder = format!("(Melds with {}.)", if card == top { bottom } else { top });
+                let text = result.get("rule text").map(Data::render).unwrap_or_default();
+                if !text.contains("Melds with") {
+                    result.set("rule text", if text.is_empty() { reminder } else { format!("{}\n{}", text, reminder) });
+                }
+            }
+        }
+        if let Some(flavor) = face_text.and_then(|face_text| face_text.flavor.as_ref()) {
+            push_alt!("flavor text", flavor);
+        }
         //TODO layouts and mana symbol watermarks for vanilla cards
         // P/T, loyalty/stability, hand/life modifier
         match mse_game {
@@ -335,6 +470,11 @@ impl DataFile {
                     if let Some(loyalty) = card.loyalty() {
                         push_alt!("loyalty", loyalty);
                     }
+                } else if frame == Some(ModernFrame::Battle) {
+                    // battles are imported with their defense as loyalty, see mtg_json::legacy_card
+                    if let Some(defense) = card.loyalty() {
+                        push_alt!("defense", defense);
+                    }
                 } else {
                     if let Some((power, toughness)) = card.pt() {
                         push_alt!("power", power);
@@ -359,29 +499,26 @@ impl DataFile {
         if !alt {
             let stylesheet = match mse_game {
                 MseGame::Magic => match card.layout() {
-                    Layout::Normal => {
-                        if card.type_line() >= CardType::Plane || card.type_line() >= CardType::Phenomenon {
-                            "m15-mainframe-planes"
-                        } else if card.type_line() >= EnchantmentType::Saga || card.type_line() >= EnchantmentType::Discovery {
-                            "m15-saga"
-                        } else if card.type_line() >= CardType::Planeswalker {
-                            "m15-mainframe-planeswalker"
-                        } else if card.is_leveler() {
-                            "m15-leveler"
-                        } else if card.type_line() >= CardType::Conspiracy {
-                            "m15-ttk-conspiracy"
-                        } else {
-                            "m15-altered"
-                        }
-                    }
+                    Layout::Normal => single_faced_stylesheet(card),
+                    Layout::Split { .. } if frame == Some(ModernFrame::Room) => ModernFrame::Room.stylesheet(),
                     Layout::Split { right, .. } => if right.abilities().into_iter().any(|abil| abil == KeywordAbility::Aftermath) {
                         "m15-aftermath"
                     } else {
                         "m15-split-fusable"
                     },
                     Layout::Flip { .. } => "m15-flip",
-                    Layout::DoubleFaced { .. } => "m15-mainframe-dfc",
-                    Layout::Meld { .. } => "m15-mainframe-dfc",
+                    Layout::DoubleFaced { .. } => if separate_faces {
+                        single_faced_stylesheet(card)
+                    } else if frame == Some(ModernFrame::Battle) {
+                        ModernFrame::Battle.stylesheet()
+                    } else {
+                        "m15-mainframe-dfc"
+                    },
+                    Layout::Meld { back, .. } => match meld_parts {
+                        Some(_) if args.meld == MeldMode::Combined && *card == back => MELD_STYLESHEET,
+                        Some(_) => single_faced_stylesheet(card),
+                        None => "m15-mainframe-dfc"
+                    },
                     Layout::Adventure { .. } => "m15-flip" //TODO use adventure frame
                 },
                 MseGame::Archenemy => "standard",
@@ -439,6 +576,75 @@ impl DataFile {
         result
     }
 
+    /// Moves parts of the rules text of a card with one of the newer frames into the separate fields of its template.
+    fn split_text_boxes(&mut self, frame: ModernFrame, abilities: &[Ability]) {
+        let text = self.get("rule text").map(Data::render).unwrap_or_default();
+        match frame {
+            ModernFrame::Battle | ModernFrame::Room => {}
+            ModernFrame::Case => {
+                let mut rule_text = Vec::default();
+                for line in text.lines() {
+                    if let Some(condition) = line.strip_prefix("To solve — ") {
+                        self.push("to solve text", condition);
+                    } else if let Some(solved) = line.strip_prefix("Solved — ") {
+                        self.push("solved text", solved);
+                    } else {
+                        rule_text.push(line);
+                    }
+                }
+                self.set("rule text", rule_text.join("\n"));
+            }
+            ModernFrame::Class => {
+                // the first level has no cost, each following level starts with a line like “{1}{W}: Level 2”
+                let level_regex = Regex::new("^(.+): Level [0-9]+$").expect("failed to compile class level regex");
+                let level_cost_regex = Regex::new("^((?:\\{[^}]+\\})+): Level [0-9]+$").expect("failed to compile class level cost regex");
+                // the rules text already contains symbol markup, so the costs are taken from the Oracle abilities like the casting cost
+                let mut costs = abilities.iter()
+                    .filter_map(|ability| level_cost_regex.captures(&ability.to_string()).and_then(|captures| captures[1].parse::<ManaCost>().ok()))
+                    .map(cost_to_mse);
+                let mut levels = vec![(None, Vec::default())];
+                for line in text.lines() {
+                    if level_regex.is_match(line) {
+                        levels.push((costs.next(), Vec::default()));
+                    } else if let Some((_, lines)) = levels.last_mut() {
+                        lines.push(line);
+                    }
+                }
+                for (i, (cost, lines)) in levels.into_iter().enumerate() {
+                    if i == 0 {
+                        self.set("rule text", lines.join("\n"));
+                    } else {
+                        if let Some(cost) = cost {
+                            self.push(format!("level {} cost", i + 1), cost);
+                        }
+                        self.push(format!("level {} text", i + 1), lines.join("\n"));
+                    }
+                }
+            }
+            ModernFrame::Dungeon => {
+                // each line is a room
+                self.items.retain(|(key, _)| key != "rule text");
+                for (i, room) in text.lines().enumerate() {
+                    self.push(format!("level {} text", i + 1), room);
+                }
+            }
+            ModernFrame::Prototype => {
+                let prototype_regex = Regex::new("^Prototype ((?:\\{[^}]+\\})+) — ([^/ ]+)/([^ ]+)").expect("failed to compile prototype regex");
+                for ability in abilities {
+                    if let Some(captures) = prototype_regex.captures(&ability.to_string()) {
+                        if let Ok(mana_cost) = captures[1].parse() {
+                            self.push("casting cost 2", cost_to_mse(mana_cost));
+                        }
+                        self.push("power 2", &captures[2]);
+                        self.push("toughness 2", &captures[3]);
+                    }
+                }
+                // the prototype is shown in the template's sidebar instead
+                self.set("rule text", text.lines().filter(|line| !line.starts_with("Prototype")).join("\n"));
+            }
+        }
+    }
+
     fn contains(&self, key: impl ToString) -> bool {
         let key = key.to_string();
         self.items.iter().any(|(k, _)| *k == key)
@@ -456,6 +662,16 @@ impl DataFile {
         self.items.push((key.to_string(), value.into()));
     }
 
+    /// Replaces the value of the first entry with the given key, or adds a new entry if there is none.
+    fn set(&mut self, key: impl ToString, value: impl Into<Data>) {
+        let key = key.to_string();
+        if let Some((_, v)) = self.items.iter_mut().find(|(k, _)| *k == key) {
+            *v = value.into();
+        } else {
+            self.items.push((key, value.into()));
+        }
+    }
+
     fn push_styling(&mut self, args: &ArgsRegular, stylesheet: &str, key: impl ToString, value: impl Into<Data>) {
         if !self.contains("styling data") {
             self.push("has styling", "true");
@@ -505,6 +721,7 @@ impl DataFile {
             zip.start_file(format!("image{}", i), FileOptions::default())?;
             io::copy(&mut image, &mut zip).at_unknown()?;
         }
+        art_handler.save_downloads()?;
         Ok(())
     }
 }
@@ -549,6 +766,73 @@ impl<K: ToString> IndexMut<K> for DataFile {
     }
 }
 
+/// The stylesheet for a card with the normal layout, or a part of a meld pair generated on its own.
+fn single_faced_stylesheet(card: &Card) -> &'static str {
+    if let Some(frame) = ModernFrame::for_card(card) {
+        frame.stylesheet()
+    } else if card.type_line() >= CardType::Plane || card.type_line() >= CardType::Phenomenon {
+        "m15-mainframe-planes"
+    } else if card.type_line() >= EnchantmentType::Saga || card.type_line() >= EnchantmentType::Discovery {
+        "m15-saga"
+    } else if card.type_line() >= CardType::Planeswalker {
+        "m15-mainframe-planeswalker"
+    } else if card.is_leveler() {
+        "m15-leveler"
+    } else if card.type_line() >= CardType::Conspiracy {
+        "m15-ttk-conspiracy"
+    } else {
+        "m15-altered"
+    }
+}
+
+/// Frames of cards which the `mtg` crate doesn't have layouts or types for, recognized by their type lines and rules text instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ModernFrame {
+    /// Double-faced, with the front face rotated sideways.
+    Battle,
+    Case,
+    /// Enchantments with levels which are gained one at a time.
+    Class,
+    Dungeon,
+    /// Artifacts which can be cast with a different mana cost and P/T.
+    Prototype,
+    /// Split cards with two doors.
+    Room
+}
+
+impl ModernFrame {
+    pub(crate) fn for_card(card: &Card) -> Option<ModernFrame> {
+        let type_line = card.type_line().to_string();
+        let has_type = |type_name: &str| type_line.split(|c: char| !c.is_alphanumeric()).any(|word| word == type_name);
+        if has_type("Battle") {
+            Some(ModernFrame::Battle)
+        } else if has_type("Case") {
+            Some(ModernFrame::Case)
+        } else if has_type("Class") {
+            Some(ModernFrame::Class)
+        } else if has_type("Dungeon") {
+            Some(ModernFrame::Dungeon)
+        } else if has_type("Room") {
+            Some(ModernFrame::Room)
+        } else if card.abilities().into_iter().any(|ability| ability.to_string().starts_with("Prototype ")) {
+            Some(ModernFrame::Prototype)
+        } else {
+            None
+        }
+    }
+
+    fn stylesheet(&self) -> &'static str {
+        match self {
+            ModernFrame::Battle => "m15-battle",
+            ModernFrame::Case => "m15-case",
+            ModernFrame::Class => "m15-class",
+            ModernFrame::Dungeon => "m15-dungeon",
+            ModernFrame::Prototype => "m15-prototype",
+            ModernFrame::Room => "m15-room"
+        }
+    }
+}
+
 fn ability_lines(abilities: &[Ability]) -> Vec<String> {
     let mut lines = Vec::default();
     let mut current_keywords = None::<String>;
@@ -606,6 +890,29 @@ fn ability_lines(abilities: &[Ability]) -> Vec<String> {
     lines
 }
 
+/// Converts an MTG JSON rarity to the corresponding MSE rarity.
+fn mse_rarity(rarity: &str) -> Option<&'static str> {
+    match rarity {
+        "basic" => Some("basic land"),
+        "common" => Some("common"),
+        "uncommon" => Some("uncommon"),
+        "rare" => Some("rare"),
+        "mythic" => Some("mythic rare"),
+        "special" | "bonus" => Some("special"),
+        _ => None
+    }
+}
+
+/// The mana value of a card face, with `X` counted as 0.
+pub(crate) fn mana_value(card: &Card) -> usize {
+    card.mana_cost().map_or(0, |mana_cost| mana_cost.symbols().into_iter().map(|symbol| match symbol {
+        ManaSymbol::Variable => 0,
+        ManaSymbol::Generic(n) => n as usize,
+        ManaSymbol::TwobridWhite | ManaSymbol::TwobridBlue | ManaSymbol::TwobridBlack | ManaSymbol::TwobridRed | ManaSymbol::TwobridGreen => 2,
+        _ => 1
+    }).sum())
+}
+
 fn cost_to_mse(cost: ManaCost) -> String {
     cost.symbols().into_iter().map(|symbol| match symbol {
         ManaSymbol::Variable => format!("X"),
@@ -677,6 +984,34 @@ fn symbols_to_mse(text: &str) -> String {
     }
 }
 
+/// Splits a replacement rules text (see `--text` and `--language`) into one text box per ability for planeswalkers and Sagas, or into the text before the first level and the text of each level for level up cards.
+///
+/// Returns `None` if this doesn't result in `num_boxes` text boxes, the number used for the Oracle text.
+fn replacement_text_boxes(text: &str, is_leveler: bool, num_boxes: usize) -> Option<Vec<String>> {
+    let lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let text_boxes = if is_leveler {
+        // level headers are translated, but their numbers aren't
+        let level_regex = Regex::new("^\\D*[0-9]+(?:-[0-9]+|\\+)$").expect("failed to compile level header regex");
+        let pt_regex = Regex::new("^[0-9*+-]+/[0-9*+-]+$").expect("failed to compile level P/T regex");
+        let mut text_boxes = vec![Vec::default()];
+        let mut lines = lines.peekable();
+        while let Some(line) = lines.next() {
+            if level_regex.is_match(line) && lines.peek().map_or(false, |next| pt_regex.is_match(next)) {
+                lines.next(); // power and toughness are taken from the Oracle text
+                text_boxes.push(Vec::default());
+            } else {
+                text_boxes.last_mut().expect("there is always a text box").push(with_mse_symbols(line));
+            }
+        }
+        text_boxes.into_iter().map(|lines| lines.join("\n")).collect::<Vec<_>>()
+    } else {
+        // chapter numbers are added by the template
+        let chapter_regex = Regex::new("^[IVX]+(?:, [IVX]+)* — ").expect("failed to compile chapter regex");
+        lines.map(|line| with_mse_symbols(chapter_regex.replace(line, ""))).collect()
+    };
+    if text_boxes.len() == num_boxes { Some(text_boxes) } else { None }
+}
+
 fn with_mse_symbols(text: impl ToString) -> String {
     let symbols_regex = Regex::new("^([\"']?)(\\{.+\\})([:.,]?[\"']*)$").expect("failed to compile symbols regex");
     let number_regex = Regex::new("^[0-9]+|[XVI]+$").expect("failed to compile number regex");
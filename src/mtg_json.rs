@@ -0,0 +1,130 @@
+//! Detection of MTG JSON file flavors, and conversion of MTG JSON v5 files to the legacy AllSets format understood by `mtg::card::Db`.
+
+use {
+    std::path::Path,
+    serde_json::{
+        Map,
+        Value,
+        json
+    },
+    crate::util::Error
+};
+
+/// AtomicCards files don't have any set information, so all cards are added to a single set with this code, which isn't used by any real set.
+const ATOMIC_CARDS_SET_CODE: &str = "ATOMIC";
+
+/// Converts the contents of an MTG JSON file to the legacy AllSets format, i.e. an object mapping set codes to sets.
+///
+/// Understood are legacy AllSets and individual set files, as well as v5 AllPrintings, AtomicCards, and individual set files. `path` is used to guess the set code of set files which don't include it, and for error messages.
+pub(crate) fn to_all_sets(json: Value, path: &Path) -> Result<Value, Error> {
+    let unsupported = || Error::UnsupportedDb(path.to_owned());
+    match json {
+        Value::Object(mut file) => if file.contains_key("meta") {
+            // v5 files wrap their contents in a `data` field
+            match file.remove("data").ok_or_else(unsupported)? {
+                Value::Object(data) => if is_set(&data) {
+                    let code = set_code(&data, path).ok_or_else(unsupported)?;
+                    Ok(json!({ code: legacy_set(data) }))
+                } else if data.values().all(is_set_value) {
+                    Ok(Value::Object(data.into_iter().map(|(code, set)| (code, match set {
+                        Value::Object(set) => legacy_set(set),
+                        set => set
+                    })).collect()))
+                } else if data.values().all(Value::is_array) {
+                    Ok(atomic_cards(data))
+                } else {
+                    Err(unsupported())
+                },
+                _ => Err(unsupported())
+            }
+        } else if is_set(&file) {
+            let code = set_code(&file, path).ok_or_else(unsupported)?;
+            Ok(json!({ code: file }))
+        } else if file.values().all(is_set_value) {
+            Ok(Value::Object(file))
+        } else {
+            Err(unsupported())
+        },
+        _ => Err(unsupported())
+    }
+}
+
+fn is_set(set: &Map<String, Value>) -> bool {
+    set.get("cards").map_or(false, Value::is_array)
+}
+
+fn is_set_value(set: &Value) -> bool {
+    set.as_object().map_or(false, is_set)
+}
+
+/// The set code of an individual set file, falling back to the file name.
+pub(crate) fn set_code(set: &Map<String, Value>, path: &Path) -> Option<String> {
+    set.get("code").and_then(Value::as_str)
+        .or_else(|| path.file_stem().and_then(|stem| stem.to_str()))
+        .map(str::to_owned)
+}
+
+fn legacy_set(mut set: Map<String, Value>) -> Value {
+    for key in &["cards", "tokens"] {
+        if let Some(Value::Array(cards)) = set.get_mut(*key) {
+            for card in cards {
+                if let Value::Object(card) = card {
+                    legacy_card(card);
+                }
+            }
+        }
+    }
+    Value::Object(set)
+}
+
+/// In v5, multi-faced cards are named after all faces, with the name of the individual face in `faceName`. The legacy format uses the face name and lists all names in `names`.
+fn legacy_card(card: &mut Map<String, Value>) {
+    if let Some(face_name) = card.remove("faceName") {
+        if let Some(Value::String(full_name)) = card.insert(format!("name"), face_name) {
+            card.entry("names").or_insert_with(|| json!(full_name.split(" // ").collect::<Vec<_>>()));
+        }
+    }
+    if !card.contains_key("convertedManaCost") {
+        if let Some(mana_value) = card.get("manaValue").cloned() {
+            card.insert(format!("convertedManaCost"), mana_value);
+        }
+    }
+    if let Some(layout) = card.get("layout").and_then(Value::as_str).and_then(legacy_layout) {
+        card.insert(format!("layout"), Value::String(layout.to_owned()));
+    }
+    // mtg::card::Card has no defense, so battles use loyalty instead, see mse::ModernFrame
+    if !card.contains_key("loyalty") {
+        if let Some(defense) = card.get("defense").cloned() {
+            card.insert(format!("loyalty"), defense);
+        }
+    }
+}
+
+/// Maps layouts which were added after the legacy format to the closest layout known to `mtg::card::Db`. Their frames are recognized by `mse::ModernFrame` instead.
+pub(crate) fn legacy_layout(layout: &str) -> Option<&'static str> {
+    match layout {
+        "case" | "class" | "dungeon" | "prototype" => Some("normal"),
+        "battle" => Some("transform"),
+        _ => None
+    }
+}
+
+/// AtomicCards maps card names to their faces, without set information. All cards are added to a single set, see `ATOMIC_CARDS_SET_CODE`.
+fn atomic_cards(data: Map<String, Value>) -> Value {
+    let mut cards = Vec::default();
+    for faces in data.into_iter().filter_map(|(_, faces)| if let Value::Array(faces) = faces { Some(faces) } else { None }) {
+        for face in faces {
+            if let Value::Object(mut card) = face {
+                legacy_card(&mut card);
+                cards.push(Value::Object(card));
+            }
+        }
+    }
+    json!({
+        ATOMIC_CARDS_SET_CODE: {
+            "code": ATOMIC_CARDS_SET_CODE,
+            "name": "Atomic Cards",
+            "cards": cards
+        }
+    })
+}
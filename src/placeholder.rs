@@ -0,0 +1,231 @@
+//! Generated artwork for cards without an image, see `--placeholder-art`.
+
+use {
+    std::io::{
+        Cursor,
+        prelude::*
+    },
+    image::{
+        DynamicImage,
+        ImageOutputFormat,
+        Rgb,
+        RgbImage
+    },
+    itertools::Itertools as _,
+    mtg::{
+        card::Card,
+        color::Color,
+        cost::ManaSymbol
+    },
+    crate::{
+        art::{
+            ImageProvider,
+            ImageSource
+        },
+        util::Error
+    }
+};
+
+/// Same size as a Scryfall art crop.
+const WIDTH: u32 = 626;
+const HEIGHT: u32 = 457;
+const MARGIN: u32 = 24;
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+const WHITE: Rgb<u8> = Rgb([248, 231, 185]);
+const BLUE: Rgb<u8> = Rgb([14, 104, 171]);
+const BLACK: Rgb<u8> = Rgb([60, 50, 45]);
+const RED: Rgb<u8> = Rgb([211, 32, 42]);
+const GREEN: Rgb<u8> = Rgb([0, 115, 62]);
+const COLORLESS: Rgb<u8> = Rgb([160, 160, 160]);
+const TEXT: Rgb<u8> = Rgb([255, 255, 255]);
+const SHADOW: Rgb<u8> = Rgb([0, 0, 0]);
+
+/// A 5×7 bitmap font. Each row is stored in the lower 5 bits of a byte, most significant bit leftmost.
+const FONT: [(char, [u8; 7]); 46] = [
+    ('A', [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('B', [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e]),
+    ('C', [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e]),
+    ('D', [0x1e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1e]),
+    ('E', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f]),
+    ('F', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10]),
+    ('G', [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f]),
+    ('H', [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('I', [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f]),
+    ('M', [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('P', [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10]),
+    ('Q', [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d]),
+    ('R', [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11]),
+    ('S', [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e]),
+    ('T', [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a]),
+    ('X', [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04]),
+    ('Z', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f]),
+    ('0', [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e]),
+    ('1', [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('2', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f]),
+    ('3', [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e]),
+    ('4', [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02]),
+    ('5', [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e]),
+    ('6', [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e]),
+    ('7', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e]),
+    ('9', [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c]),
+    ('\'', [0x0c, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('"', [0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('-', [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00]),
+    (':', [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('?', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('&', [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d])
+];
+
+/// The name of the placeholder art provider. It isn't selectable in `--image-sources` since it's always tried last.
+pub(crate) const NAME: &str = "placeholder";
+
+#[derive(Debug)]
+pub(crate) struct PlaceholderArt;
+
+impl ImageProvider for PlaceholderArt {
+    fn name(&self) -> &str { NAME }
+
+    fn find_image(&self, _: &Card) -> Option<Box<dyn ImageSource>> {
+        Some(Box::new(PlaceholderImage))
+    }
+}
+
+#[derive(Debug)]
+struct PlaceholderImage;
+
+impl ImageSource for PlaceholderImage {
+    fn open(&mut self, card: &Card) -> Result<Box<dyn Read>, Error> {
+        let mut buf = Vec::default();
+        DynamicImage::ImageRgb8(render(card)).write_to(&mut buf, ImageOutputFormat::Png)?;
+        Ok(Box::new(Cursor::new(buf)))
+    }
+}
+
+/// A horizontal gradient through the card's colors, with the card name and “art missing” written on top.
+fn render(card: &Card) -> RgbImage {
+    let colors = card_colors(card);
+    let mut image = RgbImage::from_fn(WIDTH, HEIGHT, |x, _| {
+        if colors.len() == 1 { return colors[0]; }
+        let pos = f64::from(x) / f64::from(WIDTH - 1) * (colors.len() - 1) as f64;
+        let idx = (pos.floor() as usize).min(colors.len() - 2);
+        let t = pos - idx as f64;
+        let Rgb([r1, g1, b1]) = colors[idx];
+        let Rgb([r2, g2, b2]) = colors[idx + 1];
+        let mix = |c1: u8, c2: u8| (f64::from(c1) * (1.0 - t) + f64::from(c2) * t).round() as u8;
+        Rgb([mix(r1, r2), mix(g1, g2), mix(b1, b2)])
+    });
+    let name = card.to_string();
+    let name_scale = text_scale(&name, 6);
+    let art_missing_scale = text_scale("art missing", 4);
+    let total_height = (name_scale + art_missing_scale) * GLYPH_HEIGHT + 3 * art_missing_scale;
+    let name_y = (HEIGHT - total_height) / 2;
+    draw_text(&mut image, &name, name_scale, name_y);
+    draw_text(&mut image, "art missing", art_missing_scale, name_y + name_scale * GLYPH_HEIGHT + 3 * art_missing_scale);
+    image
+}
+
+/// The card's colors in WUBRG order, based on its mana cost and color indicator.
+fn card_colors(card: &Card) -> Vec<Rgb<u8>> {
+    let mut colors = Vec::default();
+    if let Some(mana_cost) = card.mana_cost() {
+        for symbol in mana_cost.symbols() {
+            colors.extend(match symbol {
+                ManaSymbol::Variable | ManaSymbol::Generic(_) | ManaSymbol::Snow | ManaSymbol::Runic | ManaSymbol::Colorless => vec![],
+                ManaSymbol::White | ManaSymbol::TwobridWhite | ManaSymbol::PhyrexianWhite => vec![WHITE],
+                ManaSymbol::Blue | ManaSymbol::TwobridBlue | ManaSymbol::PhyrexianBlue => vec![BLUE],
+                ManaSymbol::Black | ManaSymbol::TwobridBlack | ManaSymbol::PhyrexianBlack => vec![BLACK],
+                ManaSymbol::Red | ManaSymbol::TwobridRed | ManaSymbol::PhyrexianRed => vec![RED],
+                ManaSymbol::Green | ManaSymbol::TwobridGreen | ManaSymbol::PhyrexianGreen => vec![GREEN],
+                ManaSymbol::HybridWhiteBlue => vec![WHITE, BLUE],
+                ManaSymbol::HybridBlueBlack => vec![BLUE, BLACK],
+                ManaSymbol::HybridBlackRed => vec![BLACK, RED],
+                ManaSymbol::HybridRedGreen => vec![RED, GREEN],
+                ManaSymbol::HybridGreenWhite => vec![WHITE, GREEN],
+                ManaSymbol::HybridWhiteBlack => vec![WHITE, BLACK],
+                ManaSymbol::HybridBlueRed => vec![BLUE, RED],
+                ManaSymbol::HybridBlackGreen => vec![BLACK, GREEN],
+                ManaSymbol::HybridRedWhite => vec![WHITE, RED],
+                ManaSymbol::HybridGreenBlue => vec![BLUE, GREEN]
+            });
+        }
+    }
+    if let Some(indicator) = card.color_indicator() {
+        colors.extend(indicator.canonical_order().into_iter().map(|color| match color {
+            Color::White => WHITE,
+            Color::Blue => BLUE,
+            Color::Black => BLACK,
+            Color::Red => RED,
+            Color::Green => GREEN
+        }));
+    }
+    let colors = [WHITE, BLUE, BLACK, RED, GREEN].iter().copied().filter(|color| colors.contains(color)).collect_vec();
+    if colors.is_empty() { vec![COLORLESS] } else { colors }
+}
+
+/// The largest scale (up to `max_scale`) at which the text fits on one line.
+fn text_scale(text: &str, max_scale: u32) -> u32 {
+    let text_width = text_width(text, 1);
+    ((WIDTH - 2 * MARGIN) / text_width.max(1)).max(1).min(max_scale)
+}
+
+fn text_width(text: &str, scale: u32) -> u32 {
+    let len = text.chars().flat_map(fold_char).count() as u32;
+    (len * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale
+}
+
+/// Draws the text horizontally centered with its top edge at `y`, with a drop shadow for legibility.
+fn draw_text(image: &mut RgbImage, text: &str, scale: u32, y: u32) {
+    let x = WIDTH.saturating_sub(text_width(text, scale)) / 2;
+    for &(offset, color) in &[(scale.max(2) / 2, SHADOW), (0, TEXT)] {
+        for (i, c) in text.chars().flat_map(fold_char).enumerate() {
+            let glyph = match FONT.iter().find(|&&(glyph_char, _)| glyph_char == c) {
+                Some((_, glyph)) => glyph,
+                None => continue // space or unsupported character
+            };
+            let glyph_x = x + i as u32 * (GLYPH_WIDTH + 1) * scale + offset;
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 { continue; }
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            let (px, py) = (glyph_x + col * scale + dx, y + row as u32 * scale + dy + offset);
+                            if px < WIDTH && py < HEIGHT {
+                                image.put_pixel(px, py, color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Converts a character to the uppercase ASCII characters supported by the font, removing diacritics.
+fn fold_char(c: char) -> Vec<char> {
+    match c {
+        'Æ' | 'æ' => vec!['A', 'E'],
+        'À' | 'Á' | 'Â' | 'Ä' | 'à' | 'á' | 'â' | 'ä' => vec!['A'],
+        'È' | 'É' | 'Ê' | 'è' | 'é' | 'ê' => vec!['E'],
+        'Í' | 'í' => vec!['I'],
+        'Ó' | 'Ö' | 'ó' | 'ö' => vec!['O'],
+        'Ú' | 'Û' | 'Ü' | 'ú' | 'û' | 'ü' => vec!['U'],
+        '’' => vec!['\''],
+        c => vec![c.to_ascii_uppercase()]
+    }
+}
@@ -0,0 +1,656 @@
+//! Resolving [Lore Seeker syntax](https://lore-seeker.cards/help/syntax) queries.
+//!
+//! A useful subset of the syntax is evaluated directly against the loaded card database, see `Query`. Other queries are passed to a `find_cards` script (see `--find-cards`) or to the Lore Seeker website.
+
+use {
+    std::{
+        collections::BTreeSet,
+        io,
+        iter::Peekable,
+        path::{
+            Path,
+            PathBuf
+        },
+        process::Command,
+        str::{
+            Chars,
+            FromStr
+        }
+    },
+    gitdir::Host as _,
+    itertools::Itertools as _,
+    lazy_static::lazy_static,
+    mtg::{
+        card::{
+            Card,
+            Layout,
+            Rarity
+        },
+        cardtype::CardType,
+        color::Color,
+        cost::ManaSymbol
+    },
+    regex::Regex,
+    reqwest::blocking::Client,
+    url::Url,
+    crate::{
+        db::SetIndex,
+        mse::mana_value,
+        util::{
+            CommandExt as _,
+            CommandOutputExt as _,
+            Error
+        }
+    }
+};
+
+/// The search box on every Lore Seeker page, used to recognize pages without any card titles.
+const LORE_SEEKER_SEARCH_BOX: &str = "name=\"q\"";
+/// The markup of a card title, which is matched in more detail by `LORE_SEEKER_CARD_TITLE_REGEX`.
+const LORE_SEEKER_CARD_TITLE_CLASS: &str = "<h3 class=\"card_title\">";
+
+lazy_static! {
+    /// The title of a card on a Lore Seeker search results or card page, linking to the card's page.
+    static ref LORE_SEEKER_CARD_TITLE_REGEX: Regex = Regex::new("<h3 class=\"card_title\">\\s*<a href=\"([^\"]*/card/[^/\"]+/[^/\"]+)\"[^>]*>([^<]+)</a>").expect("failed to build Lore Seeker card title regex");
+    static ref TERM_REGEX: Regex = Regex::new("^([A-Za-z]+)(:|!=|<=|>=|=|<|>)(.+)$").expect("failed to build query term regex");
+    static ref SYMBOL_REGEX: Regex = Regex::new("\\{([^}]+)\\}").expect("failed to build mana symbol regex");
+}
+
+/// The default `find_cards` script in `--offline` mode, from the gitdir master for Lore Seeker.
+pub fn default_find_cards() -> Result<PathBuf, Error> {
+    Ok(gitdir::GitHub.repo("fenhl/lore-seeker").master()?.join("search-engine").join("bin").join("find_cards"))
+}
+
+/// Runs the given magic-search-engine `find_cards` script and returns the names of the matching cards.
+///
+/// The script is run using `ruby` rather than directly so this also works on Windows.
+pub fn find_cards(find_cards: &Path, query: &str) -> Result<Vec<String>, Error> {
+    if !find_cards.is_file() { return Err(Error::MissingFindCards(find_cards.to_owned())); }
+    let output = match Command::new("ruby").arg(find_cards).arg(query).create_no_window().check("find_cards") {
+        Ok(output) => output,
+        Err(Error::Annotated(_, e)) if matches!(*e, Error::Io(ref e, _) if e.kind() == io::ErrorKind::NotFound) => return Err(Error::MissingRuby),
+        Err(e) => return Err(e)
+    };
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect())
+}
+
+/// Resolves a query using the Lore Seeker website at the given base URL, e.g. a local instance given via `--lore-seeker-url`, and returns the names of the matching cards along with the URLs of their Lore Seeker pages.
+///
+/// Result pages are requested until one doesn't contain any new cards. A query with a single result redirects to that card's page, which is handled the same way.
+///
+/// Lore Seeker has no structured search API, so the HTML is checked for the expected markup, and an error is returned instead of an empty result if it's missing.
+pub fn lore_seeker(client: &Client, base_url: &Url, query: &str) -> Result<Vec<(String, Url)>, Error> {
+    let mut results = Vec::<(String, Url)>::default();
+    for page in 1.. {
+        let mut url = base_url.join("card")?;
+        url.query_pairs_mut().append_pair("q", query).append_pair("page", &page.to_string());
+        let resp = client.get(url).send()?.error_for_status()?;
+        let page_url = resp.url().clone();
+        let html = resp.text()?;
+        // every Lore Seeker page has the search box, and every card title should be matched by the regex
+        if !html.contains(LORE_SEEKER_SEARCH_BOX) || LORE_SEEKER_CARD_TITLE_REGEX.find_iter(&html).count() != html.matches(LORE_SEEKER_CARD_TITLE_CLASS).count() {
+            return Err(Error::UnexpectedLoreSeekerPage(page_url));
+        }
+        let num_results = results.len();
+        for captures in LORE_SEEKER_CARD_TITLE_REGEX.captures_iter(&html) {
+            let card_name = unescape_html(captures[2].trim());
+            if !results.iter().any(|(name, _)| *name == card_name) {
+                results.push((card_name, page_url.join(&captures[1])?));
+            }
+        }
+        if results.len() == num_results { break; }
+    }
+    Ok(results)
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&#39;", "'")
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// A query which can be evaluated without any external service.
+///
+/// Supported are card names, `t:`, `c:`, `ci:`/`id:`, `mv`/`cmc`, `o:`, `r:`, `e:`/`set:`, `pow`, `tou`, `loy`, and `is:`, combined using `or`, `-`/`not`, and parentheses. Parsing fails for any other syntax.
+#[derive(Debug, Clone)]
+pub struct Query(Expr);
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Query, Error> {
+        let mut parser = Parser { tokens: tokenize(s)?, pos: 0 };
+        let expr = parser.parse_or()?;
+        if parser.pos < parser.tokens.len() { return Err(Error::QuerySyntax(format!("unmatched closing parenthesis"))); }
+        Ok(Query(expr))
+    }
+}
+
+impl Query {
+    /// Returns the names of all of the given cards which match this query, in alphabetical order.
+    ///
+    /// A multi-part card is included if any of its parts matches.
+    pub fn resolve(&self, cards: &[Card], sets: &SetIndex) -> Vec<String> {
+        cards.iter()
+            .filter(|card| self.0.matches(card, sets))
+            .map(|card| card.primary().to_string())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Name(String),
+    ExactName(String),
+    Type(String),
+    Color(Comparison, Colors),
+    ColorIdentity(Comparison, Colors),
+    Multicolor,
+    Oracle(String),
+    Rarity(Comparison, u8),
+    Set(String),
+    Number(NumericField, Comparison, f64),
+    Is(IsFilter)
+}
+
+impl Expr {
+    fn matches(&self, card: &Card, sets: &SetIndex) -> bool {
+        match self {
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.matches(card, sets)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.matches(card, sets)),
+            Expr::Not(expr) => !expr.matches(card, sets),
+            Expr::Name(name) => card.to_string().to_lowercase().contains(name),
+            Expr::ExactName(name) => card.to_string().to_lowercase() == *name,
+            Expr::Type(type_name) => has_type(&card.type_line().to_string(), type_name),
+            Expr::Color(cmp, colors) => cmp.colors(Colors::of(card), *colors),
+            Expr::ColorIdentity(cmp, colors) => cmp.colors(Colors::identity(card), *colors),
+            Expr::Multicolor => Colors::of(card).count() >= 2,
+            Expr::Oracle(text) => {
+                let card_name = card.to_string().to_lowercase();
+                oracle_text(card).to_lowercase().contains(&text.replace('~', &card_name))
+            }
+            Expr::Rarity(cmp, rank) => cmp.numbers(f64::from(rarity_rank(card.rarity())), f64::from(*rank)),
+            Expr::Set(code) => sets.contains(code, &card.to_string()),
+            Expr::Number(field, cmp, value) => field.value(card).map_or(false, |card_value| cmp.numbers(card_value, *value)),
+            Expr::Is(filter) => filter.matches(card)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge
+}
+
+impl Comparison {
+    /// `default` is used for the `:` operator, whose meaning depends on the field.
+    fn parse(op: &str, default: Comparison) -> Comparison {
+        match op {
+            "=" => Comparison::Eq,
+            "!=" => Comparison::Ne,
+            "<" => Comparison::Lt,
+            "<=" => Comparison::Le,
+            ">" => Comparison::Gt,
+            ">=" => Comparison::Ge,
+            _ => default
+        }
+    }
+
+    fn numbers(&self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs
+        }
+    }
+
+    /// Compares color sets by inclusion, e.g. `Ge` checks whether `lhs` contains all colors in `rhs`.
+    fn colors(&self, lhs: Colors, rhs: Colors) -> bool {
+        let subset = lhs.0 & rhs.0 == lhs.0;
+        let superset = lhs.0 & rhs.0 == rhs.0;
+        match self {
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Lt => subset && lhs != rhs,
+            Comparison::Le => subset,
+            Comparison::Gt => superset && lhs != rhs,
+            Comparison::Ge => superset
+        }
+    }
+}
+
+/// A set of colors, as a bitmask in WUBRG order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Colors(u8);
+
+impl Colors {
+    const WHITE: u8 = 0b00001;
+    const BLUE: u8 = 0b00010;
+    const BLACK: u8 = 0b00100;
+    const RED: u8 = 0b01000;
+    const GREEN: u8 = 0b10000;
+
+    fn parse(s: &str) -> Option<Colors> {
+        match &*s.to_lowercase() {
+            "c" | "colorless" => return Some(Colors(0)),
+            "white" => return Some(Colors(Colors::WHITE)),
+            "blue" => return Some(Colors(Colors::BLUE)),
+            "black" => return Some(Colors(Colors::BLACK)),
+            "red" => return Some(Colors(Colors::RED)),
+            "green" => return Some(Colors(Colors::GREEN)),
+            _ => {}
+        }
+        s.chars().map(Colors::from_letter).fold_options(Colors(0), |Colors(acc), Colors(color)| Colors(acc | color))
+    }
+
+    fn from_letter(letter: char) -> Option<Colors> {
+        Some(Colors(match letter.to_ascii_lowercase() {
+            'w' => Colors::WHITE,
+            'u' => Colors::BLUE,
+            'b' => Colors::BLACK,
+            'r' => Colors::RED,
+            'g' => Colors::GREEN,
+            _ => return None
+        }))
+    }
+
+    /// The card's colors, based on its mana cost and color indicator.
+    fn of(card: &Card) -> Colors {
+        let mut colors = 0;
+        if let Some(mana_cost) = card.mana_cost() {
+            for symbol in mana_cost.symbols() {
+                colors |= match symbol {
+                    ManaSymbol::Variable | ManaSymbol::Generic(_) | ManaSymbol::Snow | ManaSymbol::Runic | ManaSymbol::Colorless => 0,
+                    ManaSymbol::White | ManaSymbol::TwobridWhite | ManaSymbol::PhyrexianWhite => Colors::WHITE,
+                    ManaSymbol::Blue | ManaSymbol::TwobridBlue | ManaSymbol::PhyrexianBlue => Colors::BLUE,
+                    ManaSymbol::Black | ManaSymbol::TwobridBlack | ManaSymbol::PhyrexianBlack => Colors::BLACK,
+                    ManaSymbol::Red | ManaSymbol::TwobridRed | ManaSymbol::PhyrexianRed => Colors::RED,
+                    ManaSymbol::Green | ManaSymbol::TwobridGreen | ManaSymbol::PhyrexianGreen => Colors::GREEN,
+                    ManaSymbol::HybridWhiteBlue => Colors::WHITE | Colors::BLUE,
+                    ManaSymbol::HybridBlueBlack => Colors::BLUE | Colors::BLACK,
+                    ManaSymbol::HybridBlackRed => Colors::BLACK | Colors::RED,
+                    ManaSymbol::HybridRedGreen => Colors::RED | Colors::GREEN,
+                    ManaSymbol::HybridGreenWhite => Colors::WHITE | Colors::GREEN,
+                    ManaSymbol::HybridWhiteBlack => Colors::WHITE | Colors::BLACK,
+                    ManaSymbol::HybridBlueRed => Colors::BLUE | Colors::RED,
+                    ManaSymbol::HybridBlackGreen => Colors::BLACK | Colors::GREEN,
+                    ManaSymbol::HybridRedWhite => Colors::WHITE | Colors::RED,
+                    ManaSymbol::HybridGreenBlue => Colors::BLUE | Colors::GREEN
+                };
+            }
+        }
+        if let Some(indicator) = card.color_indicator() {
+            for color in indicator.canonical_order() {
+                colors |= match color {
+                    Color::White => Colors::WHITE,
+                    Color::Blue => Colors::BLUE,
+                    Color::Black => Colors::BLACK,
+                    Color::Red => Colors::RED,
+                    Color::Green => Colors::GREEN
+                };
+            }
+        }
+        Colors(colors)
+    }
+
+    /// The card's color identity: its colors plus any colored mana symbols in its rules text.
+    fn identity(card: &Card) -> Colors {
+        let mut colors = Colors::of(card).0;
+        for symbol in SYMBOL_REGEX.captures_iter(&oracle_text(card)) {
+            for color in symbol[1].chars().filter_map(Colors::from_letter) {
+                colors |= color.0;
+            }
+        }
+        Colors(colors)
+    }
+
+    fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumericField {
+    ManaValue,
+    Power,
+    Toughness,
+    Loyalty
+}
+
+impl NumericField {
+    /// Returns `None` if the card doesn't have this characteristic or it's not a number, e.g. `*`.
+    fn value(&self, card: &Card) -> Option<f64> {
+        match self {
+            NumericField::ManaValue => Some(mana_value(card) as f64),
+            NumericField::Power => card.pt().and_then(|(power, _)| power.to_string().parse().ok()),
+            NumericField::Toughness => card.pt().and_then(|(_, toughness)| toughness.to_string().parse().ok()),
+            NumericField::Loyalty => card.loyalty().and_then(|loyalty| loyalty.to_string().parse().ok())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IsFilter {
+    Permanent,
+    Spell,
+    Vanilla,
+    Split,
+    Flip,
+    DoubleFaced,
+    Meld,
+    Adventure,
+    Leveler
+}
+
+impl IsFilter {
+    fn parse(s: &str) -> Option<IsFilter> {
+        Some(match &*s.to_lowercase() {
+            "permanent" => IsFilter::Permanent,
+            "spell" => IsFilter::Spell,
+            "vanilla" => IsFilter::Vanilla,
+            "split" => IsFilter::Split,
+            "flip" => IsFilter::Flip,
+            "dfc" | "transform" => IsFilter::DoubleFaced,
+            "meld" => IsFilter::Meld,
+            "adventure" => IsFilter::Adventure,
+            "leveler" => IsFilter::Leveler,
+            _ => return None
+        })
+    }
+
+    fn matches(&self, card: &Card) -> bool {
+        let type_line = card.type_line();
+        match self {
+            IsFilter::Permanent => type_line >= CardType::Artifact || type_line >= CardType::Creature || type_line >= CardType::Enchantment || type_line >= CardType::Land || type_line >= CardType::Planeswalker,
+            IsFilter::Spell => type_line >= CardType::Artifact || type_line >= CardType::Creature || type_line >= CardType::Enchantment || type_line >= CardType::Instant || type_line >= CardType::Planeswalker || type_line >= CardType::Sorcery,
+            IsFilter::Vanilla => card.abilities().is_empty(),
+            IsFilter::Split => matches!(card.layout(), Layout::Split { .. }),
+            IsFilter::Flip => matches!(card.layout(), Layout::Flip { .. }),
+            IsFilter::DoubleFaced => matches!(card.layout(), Layout::DoubleFaced { .. }),
+            IsFilter::Meld => matches!(card.layout(), Layout::Meld { .. }),
+            IsFilter::Adventure => matches!(card.layout(), Layout::Adventure { .. }),
+            IsFilter::Leveler => card.is_leveler()
+        }
+    }
+}
+
+/// Whether each word of `type_name` (which must be lowercase) is a whole word of the type line, so that e.g. `t:ape` doesn't match Shapeshifters.
+fn has_type(type_line: &str, type_name: &str) -> bool {
+    let type_line = type_line.to_lowercase();
+    let words = type_line.split(|c: char| c.is_whitespace() || c == '—').filter(|word| !word.is_empty()).collect::<Vec<_>>();
+    type_name.split_whitespace().all(|type_word| words.contains(&type_word))
+}
+
+fn oracle_text(card: &Card) -> String {
+    card.abilities().into_iter().map(|ability| ability.to_string()).join("\n")
+}
+
+fn rarity_rank(rarity: Rarity) -> u8 {
+    match rarity {
+        Rarity::Land | Rarity::Common => 0,
+        Rarity::Uncommon => 1,
+        Rarity::Rare => 2,
+        Rarity::Mythic => 3,
+        Rarity::Special => 4
+    }
+}
+
+fn parse_rarity(s: &str) -> Option<u8> {
+    Some(match &*s.to_lowercase() {
+        "c" | "common" | "l" | "land" | "basic" => 0,
+        "u" | "uncommon" => 1,
+        "r" | "rare" => 2,
+        "m" | "mythic" => 3,
+        "s" | "special" => 4,
+        _ => return None
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    Or,
+    Not,
+    Term(String)
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::default();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => { chars.next(); }
+            '(' => { chars.next(); tokens.push(Token::LParen); }
+            ')' => { chars.next(); tokens.push(Token::RParen); }
+            '-' => { chars.next(); tokens.push(Token::Not); }
+            _ => {
+                let (term, quoted) = read_term(&mut chars)?;
+                tokens.push(match &*term.to_lowercase() {
+                    "or" if !quoted => Token::Or,
+                    "and" if !quoted => continue, // conjunction is implicit
+                    "not" if !quoted => Token::Not,
+                    _ => Token::Term(term)
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads a term up to the next whitespace or parenthesis outside of double quotes. The quotes themselves are removed.
+fn read_term(chars: &mut Peekable<Chars<'_>>) -> Result<(String, bool), Error> {
+    let mut term = String::default();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    while let Some(&c) = chars.peek() {
+        if !in_quotes && (c.is_whitespace() || c == '(' || c == ')') { break; }
+        if c == '"' {
+            in_quotes = !in_quotes;
+            quoted = true;
+        } else {
+            term.push(c);
+        }
+        chars.next();
+    }
+    if in_quotes { return Err(Error::QuerySyntax(format!("unterminated quotes"))); }
+    Ok((term, quoted))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, Error> {
+        let mut alternatives = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            alternatives.push(self.parse_and()?);
+        }
+        Ok(if alternatives.len() == 1 { alternatives.remove(0) } else { Expr::Or(alternatives) })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, Error> {
+        let mut parts = Vec::default();
+        while let Some(token) = self.peek() {
+            if *token == Token::Or || *token == Token::RParen { break; }
+            parts.push(self.parse_unary()?);
+        }
+        match parts.len() {
+            0 => Err(Error::QuerySyntax(format!("expected a search term"))),
+            1 => Ok(parts.remove(0)),
+            _ => Ok(Expr::And(parts))
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, Error> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) { return Err(Error::QuerySyntax(format!("unmatched opening parenthesis"))); }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(Token::Term(term)) => parse_term(&term),
+            Some(Token::Or) | Some(Token::RParen) | None => Err(Error::QuerySyntax(format!("expected a search term")))
+        }
+    }
+}
+
+fn parse_term(term: &str) -> Result<Expr, Error> {
+    let captures = match TERM_REGEX.captures(term) {
+        Some(captures) => captures,
+        None => return Ok(if term.starts_with('!') {
+            Expr::ExactName(term[1..].to_lowercase())
+        } else {
+            Expr::Name(term.to_lowercase())
+        })
+    };
+    let key = captures[1].to_lowercase();
+    let op = &captures[2];
+    let value = &captures[3];
+    let text_op = || if op == ":" || op == "=" { Ok(()) } else { Err(Error::QuerySyntax(format!("{} can't be used with {}:", op, key))) };
+    let number = || value.parse::<f64>().map_err(|_| Error::QuerySyntax(format!("not a number: {}", value)));
+    let invalid = || Error::QuerySyntax(format!("invalid value for {}: {}", key, value));
+    Ok(match &*key {
+        "t" | "type" => { text_op()?; Expr::Type(value.to_lowercase()) }
+        "o" | "oracle" => { text_op()?; Expr::Oracle(value.to_lowercase()) }
+        "e" | "set" | "s" => { text_op()?; Expr::Set(value.to_owned()) }
+        "is" => { text_op()?; Expr::Is(IsFilter::parse(value).ok_or_else(invalid)?) }
+        "c" | "color" => match &*value.to_lowercase() {
+            "m" | "multicolor" | "multicolored" if op == ":" => Expr::Multicolor,
+            "c" | "colorless" if op == ":" => Expr::Color(Comparison::Eq, Colors(0)),
+            _ => Expr::Color(Comparison::parse(op, Comparison::Ge), Colors::parse(value).ok_or_else(invalid)?)
+        },
+        "ci" | "id" | "identity" => Expr::ColorIdentity(Comparison::parse(op, Comparison::Le), Colors::parse(value).ok_or_else(invalid)?),
+        "r" | "rarity" => Expr::Rarity(Comparison::parse(op, Comparison::Eq), parse_rarity(value).ok_or_else(invalid)?),
+        "mv" | "cmc" | "manavalue" => Expr::Number(NumericField::ManaValue, Comparison::parse(op, Comparison::Eq), number()?),
+        "pow" | "power" => Expr::Number(NumericField::Power, Comparison::parse(op, Comparison::Eq), number()?),
+        "tou" | "toughness" => Expr::Number(NumericField::Toughness, Comparison::parse(op, Comparison::Eq), number()?),
+        "loy" | "loyalty" => Expr::Number(NumericField::Loyalty, Comparison::parse(op, Comparison::Eq), number()?),
+        _ => return Err(Error::QuerySyntax(format!("unsupported search term: {}", term)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> Expr {
+        query.parse::<Query>().expect("failed to parse query").0
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(parse("t:goblin t:wizard"), Expr::And(vec![Expr::Type(format!("goblin")), Expr::Type(format!("wizard"))]));
+        assert_eq!(parse("t:goblin and t:wizard"), parse("t:goblin t:wizard"));
+        assert_eq!(parse("t:goblin OR t:wizard"), Expr::Or(vec![Expr::Type(format!("goblin")), Expr::Type(format!("wizard"))]));
+        assert_eq!(parse("t:elf (t:goblin or t:wizard)"), Expr::And(vec![
+            Expr::Type(format!("elf")),
+            Expr::Or(vec![Expr::Type(format!("goblin")), Expr::Type(format!("wizard"))])
+        ]));
+        // conjunction binds more tightly than disjunction
+        assert_eq!(parse("t:elf t:goblin or t:wizard"), Expr::Or(vec![
+            Expr::And(vec![Expr::Type(format!("elf")), Expr::Type(format!("goblin"))]),
+            Expr::Type(format!("wizard"))
+        ]));
+        assert_eq!(parse("\"or\""), Expr::Name(format!("or")));
+        assert_eq!(parse("o:\"draw a card\""), Expr::Oracle(format!("draw a card")));
+        assert_eq!(parse("!\"Lightning Bolt\""), Expr::ExactName(format!("lightning bolt")));
+    }
+
+    #[test]
+    fn negation() {
+        assert_eq!(parse("-t:land"), Expr::Not(Box::new(Expr::Type(format!("land")))));
+        assert_eq!(parse("not t:land"), parse("-t:land"));
+        assert_eq!(parse("--t:land"), Expr::Not(Box::new(Expr::Not(Box::new(Expr::Type(format!("land")))))));
+        assert_eq!(parse("-(c:r or c:g)"), Expr::Not(Box::new(Expr::Or(vec![
+            Expr::Color(Comparison::Ge, Colors(Colors::RED)),
+            Expr::Color(Comparison::Ge, Colors(Colors::GREEN))
+        ]))));
+        assert_eq!(parse("t:goblin -t:creature"), Expr::And(vec![
+            Expr::Type(format!("goblin")),
+            Expr::Not(Box::new(Expr::Type(format!("creature"))))
+        ]));
+    }
+
+    #[test]
+    fn numeric_comparisons() {
+        assert_eq!(parse("mv:3"), Expr::Number(NumericField::ManaValue, Comparison::Eq, 3.0));
+        assert_eq!(parse("cmc=3"), parse("mv:3"));
+        assert_eq!(parse("mv!=3"), Expr::Number(NumericField::ManaValue, Comparison::Ne, 3.0));
+        assert_eq!(parse("pow>=4"), Expr::Number(NumericField::Power, Comparison::Ge, 4.0));
+        assert_eq!(parse("tou<2"), Expr::Number(NumericField::Toughness, Comparison::Lt, 2.0));
+        assert_eq!(parse("loy>3"), Expr::Number(NumericField::Loyalty, Comparison::Gt, 3.0));
+        assert_eq!(parse("pow<=0.5"), Expr::Number(NumericField::Power, Comparison::Le, 0.5));
+        assert!(Comparison::Eq.numbers(3.0, 3.0));
+        assert!(!Comparison::Ne.numbers(3.0, 3.0));
+        assert!(Comparison::Lt.numbers(2.0, 3.0));
+        assert!(!Comparison::Lt.numbers(3.0, 3.0));
+        assert!(Comparison::Le.numbers(3.0, 3.0));
+        assert!(Comparison::Gt.numbers(4.0, 3.0));
+        assert!(!Comparison::Gt.numbers(3.0, 3.0));
+        assert!(Comparison::Ge.numbers(3.0, 3.0));
+        assert!(matches!("mv>x".parse::<Query>(), Err(Error::QuerySyntax(_))));
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse("c:m"), Expr::Multicolor);
+        assert_eq!(parse("c:c"), Expr::Color(Comparison::Eq, Colors(0)));
+        assert_eq!(parse("ci:wu"), Expr::ColorIdentity(Comparison::Le, Colors(Colors::WHITE | Colors::BLUE)));
+        let azorius = Colors(Colors::WHITE | Colors::BLUE);
+        let white = Colors(Colors::WHITE);
+        assert!(Comparison::Ge.colors(azorius, white));
+        assert!(Comparison::Gt.colors(azorius, white));
+        assert!(!Comparison::Gt.colors(white, white));
+        assert!(Comparison::Le.colors(white, azorius));
+        assert!(!Comparison::Le.colors(azorius, white));
+    }
+
+    #[test]
+    fn types() {
+        assert!(has_type("Creature — Ape", "ape"));
+        assert!(!has_type("Creature — Shapeshifter", "ape"));
+        assert!(has_type("Artifact", "artifact"));
+        assert!(!has_type("Artifact", "art"));
+        assert!(has_type("Legendary Artifact Creature — Golem", "legendary creature"));
+        assert!(!has_type("Legendary Artifact Creature — Golem", "legendary enchantment"));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for query in &["", "or", "(t:goblin", "t:goblin)", "\"goblin", "t<goblin", "is:foil", "frame:future"] {
+            assert!(matches!(query.parse::<Query>(), Err(Error::QuerySyntax(_))), "{:?} should be a syntax error", query);
+        }
+    }
+}
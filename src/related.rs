@@ -0,0 +1,77 @@
+//! Cards added along with the requested cards, see `--with-related`.
+
+use {
+    itertools::Itertools as _,
+    lazy_static::lazy_static,
+    mtg::card::{
+        Card,
+        Layout
+    },
+    regex::Regex,
+    crate::{
+        args::{
+            ArgsRegular,
+            MeldMode,
+            Related
+        },
+        db::{
+            NameIndex,
+            SetIndex
+        }
+    }
+};
+
+lazy_static! {
+    static ref PARTNER_WITH_REGEX: Regex = Regex::new("(?m)^Partner with ([^(\n]+?)(?: \\(|$)").expect("failed to build partner with regex");
+}
+
+/// Adds the cards related to the given cards, as configured by `--with-related`, each with the quantity of the card which requested it.
+///
+/// Tokens and emblems are added once each. Meld pairs are replaced with their two halves, so the melded back face is generated with each of them, or with all three parts if they're generated separately, see `--meld`.
+pub(crate) fn expand(cards: Vec<(Card, usize)>, args: &ArgsRegular, names: &NameIndex<'_>, sets: &SetIndex) -> Vec<(Card, usize)> {
+    let mut related = Vec::default();
+    for (card, quantity) in &cards {
+        for face in faces(card) {
+            let face_name = face.to_string();
+            let text = face.abilities().into_iter().map(|ability| ability.to_string()).join("\n");
+            if args.with_related(Related::Partners) {
+                for captures in PARTNER_WITH_REGEX.captures_iter(&text) {
+                    // partners which aren't in the database are skipped, the card itself is still generated
+                    if let Ok(partner) = names.lookup(&captures[1]) {
+                        related.push((partner, *quantity));
+                    }
+                }
+            }
+            if args.with_related(Related::Tokens) {
+                related.extend(sets.tokens_created_by(&face_name).iter().map(|token| (token.clone(), 1)));
+            }
+            // with !all, all mentioned cards are already included
+            if args.with_related(Related::Mentioned) && !args.all_command {
+                related.extend(names.mentioned_in(&text, card).into_iter().map(|mentioned| (mentioned, *quantity)));
+            }
+        }
+    }
+    cards.into_iter()
+        .chain(related)
+        .flat_map(|(card, quantity)| match card.layout() {
+            Layout::Meld { top, bottom, back } if args.with_related(Related::Parts) => if let MeldMode::Dfc = args.meld {
+                vec![(top, quantity), (bottom, quantity)]
+            } else {
+                vec![(top, quantity), (bottom, quantity), (back, quantity)]
+            },
+            _ => vec![(card.primary(), quantity)]
+        })
+        .collect()
+}
+
+/// The faces of a card which have their own rules text, e.g. both halves of a split card.
+fn faces(card: &Card) -> Vec<Card> {
+    match card.layout() {
+        Layout::Normal => vec![card.clone()],
+        Layout::Split { right: alt_part, .. } |
+        Layout::Flip { flipped: alt_part, .. } |
+        Layout::DoubleFaced { back: alt_part, .. } |
+        Layout::Meld { back: alt_part, .. } |
+        Layout::Adventure { adventure: alt_part, .. } => vec![card.clone(), alt_part]
+    }
+}
@@ -0,0 +1,242 @@
+//! Support for Scryfall bulk data files as card databases, see `--db`.
+
+use {
+    std::collections::{
+        HashMap,
+        HashSet
+    },
+    serde::Deserialize,
+    serde_json::{
+        Map,
+        Value,
+        json
+    },
+    url::Url,
+    crate::mtg_json
+};
+
+/// Layouts of Scryfall objects which aren't cards, like art cards, and are therefore skipped.
+const NON_CARD_LAYOUTS: [&str; 3] = ["art_series", "double_faced_token", "reversible_card"];
+/// Layouts which are listed as MTG JSON tokens rather than cards, see `--with-related`.
+const TOKEN_LAYOUTS: [&str; 2] = ["emblem", "token"];
+const SUPERTYPES: [&str; 5] = ["Basic", "Legendary", "Ongoing", "Snow", "World"];
+
+#[derive(Debug, Deserialize)]
+struct BulkCard {
+    name: String,
+    layout: String,
+    set: String,
+    set_name: String,
+    collector_number: String,
+    #[serde(default)]
+    lang: Option<String>,
+    rarity: String,
+    released_at: String,
+    #[serde(default)]
+    artist: Option<String>,
+    #[serde(default)]
+    mana_cost: Option<String>,
+    #[serde(default)]
+    cmc: f64,
+    #[serde(default)]
+    type_line: Option<String>,
+    #[serde(default)]
+    oracle_text: Option<String>,
+    #[serde(default)]
+    power: Option<String>,
+    #[serde(default)]
+    toughness: Option<String>,
+    #[serde(default)]
+    loyalty: Option<String>,
+    #[serde(default)]
+    defense: Option<String>,
+    #[serde(default)]
+    colors: Option<Vec<String>>,
+    #[serde(default)]
+    color_identity: Vec<String>,
+    #[serde(default)]
+    image_uris: Option<ImageUris>,
+    #[serde(default)]
+    card_faces: Vec<BulkCardFace>,
+    #[serde(default)]
+    all_parts: Vec<RelatedCard>
+}
+
+#[derive(Debug, Deserialize)]
+struct BulkCardFace {
+    name: String,
+    #[serde(default)]
+    artist: Option<String>,
+    #[serde(default)]
+    mana_cost: Option<String>,
+    #[serde(default)]
+    type_line: Option<String>,
+    #[serde(default)]
+    oracle_text: Option<String>,
+    #[serde(default)]
+    power: Option<String>,
+    #[serde(default)]
+    toughness: Option<String>,
+    #[serde(default)]
+    loyalty: Option<String>,
+    #[serde(default)]
+    defense: Option<String>,
+    #[serde(default)]
+    colors: Option<Vec<String>>,
+    #[serde(default)]
+    image_uris: Option<ImageUris>
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageUris {
+    art_crop: Url
+}
+
+#[derive(Debug, Deserialize)]
+struct RelatedCard {
+    component: String,
+    name: String
+}
+
+/// Artwork for a card face, as listed in a Scryfall bulk data file.
+#[derive(Debug, Clone)]
+pub struct ArtCrop {
+    pub url: Url,
+    pub artist: Option<String>,
+    released_at: String
+}
+
+/// Artwork from Scryfall bulk data files, so it can be downloaded without looking up each card using the Scryfall API.
+#[derive(Debug, Default)]
+pub struct ArtIndex {
+    /// The newest printing of each card face.
+    by_name: HashMap<String, ArtCrop>,
+    /// Keyed by uppercase set code and card face name.
+    by_printing: HashMap<(String, String), ArtCrop>
+}
+
+impl ArtIndex {
+    /// Returns the artwork of the given card face, preferring its printing in the given set if any.
+    pub fn get(&self, card_name: &str, set_code: Option<&str>) -> Option<&ArtCrop> {
+        set_code.and_then(|set_code| self.by_printing.get(&(set_code.to_ascii_uppercase(), card_name.to_owned())))
+            .or_else(|| self.by_name.get(card_name))
+    }
+
+    fn insert(&mut self, set_code: &str, card_name: &str, art_crop: ArtCrop) {
+        match self.by_name.get(card_name) {
+            Some(newest) if newest.released_at >= art_crop.released_at => {}
+            _ => { self.by_name.insert(card_name.to_owned(), art_crop.clone()); }
+        }
+        self.by_printing.insert((set_code.to_ascii_uppercase(), card_name.to_owned()), art_crop);
+    }
+
+    /// Adds the entries from another index, replacing existing entries for the same card faces.
+    pub(crate) fn extend(&mut self, other: ArtIndex) {
+        self.by_name.extend(other.by_name);
+        self.by_printing.extend(other.by_printing);
+    }
+}
+
+/// Checks whether a JSON file looks like Scryfall bulk data, i.e. an array of card objects.
+pub(crate) fn is_bulk_data(json: &Value) -> bool {
+    json.as_array().map_or(false, |cards| cards.first().map_or(true, |card| card.get("object").and_then(Value::as_str) == Some("card")))
+}
+
+/// Converts Scryfall bulk data, like `oracle-cards.json` or `default-cards.json`, to the MTG JSON AllSets format, and indexes its artwork.
+pub(crate) fn convert(json: Value) -> Result<(Value, ArtIndex), serde_json::Error> {
+    let mut sets = Map::default();
+    let mut art_index = ArtIndex::default();
+    let mut cards = Vec::<BulkCard>::deserialize(json)?;
+    // files like all-cards.json list each printing once per language, the English one is used where available
+    cards.sort_by_key(|card| card.lang.as_deref().map_or(false, |lang| lang != "en"));
+    let mut printings = HashSet::new();
+    for card in cards {
+        if NON_CARD_LAYOUTS.contains(&&*card.layout) { continue; }
+        if !printings.insert((card.set.clone(), card.collector_number.clone())) { continue; }
+        let set_code = card.set.to_ascii_uppercase();
+        let set = sets.entry(set_code.clone()).or_insert_with(|| json!({
+            "code": set_code,
+            "name": card.set_name,
+            "cards": [],
+            "tokens": []
+        }));
+        let is_token = TOKEN_LAYOUTS.contains(&&*card.layout);
+        let (meld_names, meld_side) = if card.layout == "meld" {
+            let names = card.all_parts.iter().filter(|part| part.component == "meld_part")
+                .chain(card.all_parts.iter().filter(|part| part.component == "meld_result"))
+                .map(|part| part.name.clone())
+                .collect();
+            // each meld part is a front face, the meld result is the back face of both
+            let side = card.all_parts.iter().find(|part| part.name == card.name).map(|part| if part.component == "meld_result" { "b" } else { "a" });
+            (names, side)
+        } else {
+            (Vec::default(), None)
+        };
+        let faces = if card.card_faces.is_empty() { vec![None] } else { card.card_faces.iter().map(Some).collect() };
+        let names = if !meld_names.is_empty() {
+            Some(meld_names)
+        } else if faces.len() > 1 {
+            Some(card.card_faces.iter().map(|face| face.name.clone()).collect::<Vec<_>>())
+        } else {
+            None
+        };
+        for (side, face) in faces.into_iter().enumerate() {
+            let name = face.map_or(&card.name, |face| &face.name);
+            let type_line = face.and_then(|face| face.type_line.as_ref()).or(card.type_line.as_ref()).cloned().unwrap_or_default();
+            let (supertypes, types, subtypes) = split_type_line(&type_line);
+            let mut mtg_json_card = json!({
+                "name": name,
+                "layout": mtg_json::legacy_layout(&card.layout).unwrap_or(&*card.layout),
+                "number": card.collector_number,
+                "rarity": card.rarity,
+                "type": type_line,
+                "supertypes": supertypes,
+                "types": types,
+                "subtypes": subtypes,
+                "convertedManaCost": card.cmc,
+                "colorIdentity": card.color_identity,
+                "colors": face.and_then(|face| face.colors.as_ref()).or(card.colors.as_ref()).cloned().unwrap_or_default()
+            });
+            let fields = mtg_json_card.as_object_mut().expect("card JSON should be an object");
+            let mut insert_opt = |key: &str, value: Option<&String>| if let Some(value) = value {
+                fields.insert(key.to_owned(), Value::String(value.clone()));
+            };
+            insert_opt("artist", face.and_then(|face| face.artist.as_ref()).or(card.artist.as_ref()));
+            insert_opt("manaCost", face.and_then(|face| face.mana_cost.as_ref()).or(card.mana_cost.as_ref()).filter(|mana_cost| !mana_cost.is_empty()));
+            insert_opt("text", face.and_then(|face| face.oracle_text.as_ref()).or(card.oracle_text.as_ref()).filter(|text| !text.is_empty()));
+            insert_opt("power", face.and_then(|face| face.power.as_ref()).or(card.power.as_ref()));
+            insert_opt("toughness", face.and_then(|face| face.toughness.as_ref()).or(card.toughness.as_ref()));
+            // mtg::card::Card has no defense, so battles use loyalty instead, see mse::ModernFrame
+            insert_opt("loyalty", face.and_then(|face| face.loyalty.as_ref().or(face.defense.as_ref())).or(card.loyalty.as_ref()).or(card.defense.as_ref()));
+            if let Some(ref names) = names {
+                fields.insert(format!("names"), json!(names));
+                fields.insert(format!("side"), json!(meld_side.map_or_else(|| ((b'a' + side as u8) as char).to_string(), str::to_owned)));
+            }
+            if is_token {
+                // a token's related parts are the cards which create it
+                fields.insert(format!("reverseRelated"), json!(card.all_parts.iter().filter(|part| part.component != "token").map(|part| &part.name).collect::<Vec<_>>()));
+            }
+            if let Some(image_uris) = face.and_then(|face| face.image_uris.as_ref()).or(card.image_uris.as_ref()) {
+                art_index.insert(&set_code, name, ArtCrop {
+                    url: image_uris.art_crop.clone(),
+                    artist: face.and_then(|face| face.artist.clone()).or_else(|| card.artist.clone()),
+                    released_at: card.released_at.clone()
+                });
+            }
+            if let Some(Value::Array(cards)) = set.get_mut(if is_token { "tokens" } else { "cards" }) {
+                cards.push(mtg_json_card);
+            }
+        }
+    }
+    Ok((Value::Object(sets), art_index))
+}
+
+/// Splits a type line like `Legendary Creature — Elf Druid` into supertypes, card types, and subtypes.
+fn split_type_line(type_line: &str) -> (Vec<&str>, Vec<&str>, Vec<&str>) {
+    let (types, subtypes) = match type_line.split_once(" — ") {
+        Some((types, subtypes)) => (types, subtypes.split_whitespace().collect()),
+        None => (type_line, Vec::default())
+    };
+    let (supertypes, types) = types.split_whitespace().partition(|card_type| SUPERTYPES.contains(card_type));
+    (supertypes, types, subtypes)
+}
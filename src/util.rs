@@ -2,6 +2,7 @@ use {
     std::{
         convert::Infallible,
         fmt,
+        fs,
         io,
         path::{
             Path,
@@ -13,6 +14,7 @@ use {
         }
     },
     derive_more::From,
+    itertools::Itertools as _,
     mtg::card::DbError
 };
 #[cfg(windows)] use std::os::windows::process::CommandExt as _;
@@ -74,12 +76,14 @@ pub enum Error {
     #[from(ignore)]
     CardGen(String, String),
     #[from(ignore)]
-    CardNotFound(String),
+    CardNotFound(String, Vec<String>),
+    Cbor(serde_cbor::Error),
     ColorParse(css_color_parser::ColorParseError),
     #[from(ignore)]
     CommandExit(&'static str, Output),
     Db(DbError),
     GitDir(gitdir::host::github::Error),
+    Image(image::ImageError),
     InvalidHeaderValue(reqwest::header::InvalidHeaderValue),
     #[from(ignore)]
     Io(io::Error, Option<PathBuf>),
@@ -87,12 +91,24 @@ pub enum Error {
     LoreSeeker(lore_seeker::Error),
     MissingAsset,
     MissingEnvar(&'static str),
+    #[from(ignore)]
+    MissingFindCards(PathBuf),
     MissingHomeDir,
     MissingPackage,
     MissingRelease,
+    MissingRuby,
+    #[from(ignore)]
+    QuerySyntax(String),
     Reqwest(reqwest::Error),
     SameVersion,
     SemVer(semver::Error),
+    #[from(ignore)]
+    UnexpectedLoreSeekerPage(url::Url),
+    #[from(ignore)]
+    UnknownSet(String),
+    #[from(ignore)]
+    UnsupportedDb(PathBuf),
+    UrlParse(url::ParseError),
     VersionCommand,
     VersionRegression,
     Zip(zip::result::ZipError)
@@ -110,11 +126,17 @@ impl fmt::Display for Error {
             Error::Annotated(msg, e) => write!(f, "{}: {}", msg, e),
             Error::Args(msg) => msg.fmt(f),
             Error::CardGen(card_name, msg) => write!(f, "error generating {}: {}", card_name, msg),
-            Error::CardNotFound(card_name) => write!(f, "no card named {:?} found", card_name),
+            Error::CardNotFound(card_name, suggestions) => if suggestions.is_empty() {
+                write!(f, "no card named {:?} found", card_name)
+            } else {
+                write!(f, "no card named {:?} found, did you mean {}?", card_name, suggestions.iter().map(|suggestion| format!("{:?}", suggestion)).join(" or "))
+            },
+            Error::Cbor(e) => write!(f, "error reading or writing the card database cache: {}", e),
             Error::ColorParse(e) => e.fmt(f),
             Error::CommandExit(cmd, ref output) => write!(f, "subprocess {} exited with status {}", cmd, output.status),
             Error::Db(e) => write!(f, "card database error: {:?}", e), //TODO impl Display for DbError
             Error::GitDir(e) => write!(f, "gitdir error: {:?}", e), //TODO impl Display for gitdir Error
+            Error::Image(e) => write!(f, "image processing error: {}", e),
             Error::InvalidHeaderValue(e) => e.fmt(f),
             Error::Io(e, Some(path)) => write!(f, "I/O error at {}: {}", path.display(), e),
             Error::Io(e, None) => write!(f, "I/O error: {}", e),
@@ -122,9 +144,12 @@ impl fmt::Display for Error {
             Error::LoreSeeker(e) => write!(f, "Lore Seeker error: {:?}", e), //TODO impl Display for lore_seeker::Error
             Error::MissingAsset => write!(f, "The downlad for your OS is missing from the latest GitHub release."),
             Error::MissingEnvar(var) => write!(f, "missing environment variable: {:?}", var),
+            Error::MissingFindCards(path) => write!(f, "The find_cards script was not found at {}. Use --find-cards to specify its location.", path.display()),
             Error::MissingHomeDir => write!(f, "Could not find your user folder."),
             Error::MissingPackage => write!(f, "The binary to be released was not found in Cargo.toml"),
             Error::MissingRelease => write!(f, "The program does not appear to be installed via `cargo install`, but no releases were found on the GitHub repo."),
+            Error::MissingRuby => write!(f, "Ruby is required to run find_cards, but the ruby command was not found. Install Ruby or make sure it's on your PATH."),
+            Error::QuerySyntax(msg) => write!(f, "unsupported query syntax: {}", msg),
             Error::Reqwest(e) => if let Some(url) = e.url() {
                 write!(f, "error downloading {}: {}", url, e)
             } else {
@@ -132,6 +157,10 @@ impl fmt::Display for Error {
             },
             Error::SameVersion => write!(f, "The release being created has the same version as the latest release."),
             Error::SemVer(e) => e.fmt(f),
+            Error::UnexpectedLoreSeekerPage(url) => write!(f, "the Lore Seeker page at {} doesn't look like a search results or card page, so Lore Seeker may have changed its layout. Try a query which is supported by the built-in query engine instead.", url),
+            Error::UnknownSet(set_code) => write!(f, "no set with code {:?} found", set_code),
+            Error::UnsupportedDb(path) => write!(f, "the card database at {} is in an unsupported format. Supported formats are MTG JSON AllSets, AllPrintings, AtomicCards, and individual set files, as well as Scryfall bulk data.", path.display()),
+            Error::UrlParse(e) => e.fmt(f),
             Error::VersionCommand => write!(f, "Could not check version of the installed update."),
             Error::VersionRegression => write!(f, "The release being created has a lower version than the latest release."),
             Error::Zip(e) => e.fmt(f)
@@ -191,3 +220,13 @@ impl<T, E: IoResultExt> IoResultExt for Result<T, E> {
         self.map_err(|e| e.at_unknown())
     }
 }
+
+/// Writes a file by writing to a temporary file in the same directory and renaming it into place, so an interrupted write doesn't leave a broken file behind.
+pub(crate) fn write_atomically(path: &Path, write: impl FnOnce(&mut fs::File) -> Result<(), Error>) -> Result<(), Error> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir).at(dir)?;
+    let mut file = tempfile::NamedTempFile::new_in(dir).at(dir)?;
+    write(file.as_file_mut())?;
+    file.persist(path).map_err(|e| e.error).at(path)?;
+    Ok(())
+}